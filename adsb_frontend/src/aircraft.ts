@@ -9,10 +9,12 @@ export class Aircraft {
     private hover: boolean = false;
     private suppress_details: boolean = false;
     public last_contact: number = Date.now();
+    /// Recent positions from oldest to newest, only fetched while the pane is expanded
+    public track: Position[] = [];
     constructor(
         public icao: number,
         public callsign: string,
-        public altitude: number,
+        public altitude: number | null,
         public pos: Position | null,
     ) { }
 
@@ -28,9 +30,13 @@ export class Aircraft {
     /**
      * Draw a aeroplane on the canvas just position icao and altitude
      */
-    public draw(ctx: CanvasRenderingContext2D) {
+    public draw(ctx: CanvasRenderingContext2D, center: Center) {
         const line_end = new PositionXY(this.pos_xy.x + 10, this.pos_xy.y - 17.5); 
 
+        if (this.extended_pane) {
+            this.draw_track(ctx, center);
+        }
+
         /// Draw dot
         ctx.fillStyle = 'white';
         ctx.beginPath();
@@ -46,7 +52,7 @@ export class Aircraft {
         
         if (!this.extended_pane && !this.hover || this.suppress_details) { 
             const icao_line = `${this.icao.toString(16)}`;
-            const altitude_line = `${this.altitude} ft`;
+            const altitude_line = this.altitude !== null ? `${this.altitude} ft` : "N/A";
 
             const text_width = Math.max(ctx.measureText(icao_line).width, ctx.measureText(altitude_line).width)
             const text_height = get_text_height(ctx, icao_line);
@@ -80,7 +86,7 @@ export class Aircraft {
         const lines = [
             `ICAO: ${this.icao.toString(16)}`,
             `Callsign: ${this.callsign}`,
-            `Altitude: ${this.altitude} ft`,
+            this.altitude !== null ? `Altitude: ${this.altitude} ft` : "Altitude: N/A",
             `Last Contact: ${new Date(this.last_contact).toLocaleTimeString()}`,
             latLonLine
         ]
@@ -107,6 +113,36 @@ export class Aircraft {
         }
     }
 
+    /**
+     * Draw the trail of recent positions up to the current position
+     * 
+     * @param ctx the canvas to draw on
+     * @param center the center point of the screen for placement
+     */
+    public draw_track(ctx: CanvasRenderingContext2D, center: Center) {
+        if (this.track.length === 0) return;
+
+        ctx.strokeStyle = 'gray';
+        ctx.beginPath();
+        this.track.forEach((pos, i) => {
+            const point = center.get_xy(pos);
+            if (i === 0) {
+                ctx.moveTo(point.x, point.y);
+            } else {
+                ctx.lineTo(point.x, point.y);
+            }
+        });
+        ctx.lineTo(this.pos_xy.x, this.pos_xy.y);
+        ctx.stroke();
+    }
+
+    /**
+     * Check if the expanded pane is shown
+     */
+    public is_expanded(): boolean {
+        return this.extended_pane;
+    }
+
     /**
      * Toggle if to show the expanded pane
      */
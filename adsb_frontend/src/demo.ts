@@ -21,17 +21,17 @@ export function create_demo_aircraft(): Aircraft[] {
 }
 
 export function update_aircraft_demo(aircraft: Aircraft[]) {
-    aircraft[0].altitude += 1
+    aircraft[0].altitude = (aircraft[0].altitude ?? 0) + 1
     if (aircraft[0].pos != null) {
         aircraft[0].pos.latitude += 0.0005
     }
     
-    aircraft[1].altitude -= 1
+    aircraft[1].altitude = (aircraft[1].altitude ?? 0) - 1
     if (aircraft[1].pos != null) {
         aircraft[1].pos.longitude += 0.0005
     }
 
-    aircraft[2].altitude += 0
+    aircraft[2].altitude = (aircraft[2].altitude ?? 0) + 0
     if (aircraft[2].pos != null) {
         aircraft[2].pos.latitude -= 0.001
     }
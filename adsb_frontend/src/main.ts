@@ -3,6 +3,8 @@ import { Center, Position, PositionXY } from "./position";
 import { create_demo_aircraft, update_aircraft_demo, create_demo_center } from "./demo";
 import { Airfield, loadAirfieldsFromCSV } from "./airfield/airfield"
 import { AircraftSummary } from "../../bindings/AircraftSummary";
+import { TrackerEvent } from "../../bindings/TrackerEvent";
+import { AircraftTrack } from "../../bindings/AircraftTrack";
 import { get_text_height } from "./utils";
 
 
@@ -24,8 +26,11 @@ const CONFIG = {
  */
 function draw_statistics(ctx: CanvasRenderingContext2D, aircraft: Aircraft[]) {
     const num_planes = aircraft.length;
-    const max_alt = Math.max(...aircraft.map(plane => plane.altitude));
-    const min_alt = Math.min(...aircraft.map(plane => plane.altitude));
+    const altitudes = aircraft
+        .map(plane => plane.altitude)
+        .filter((altitude): altitude is number => altitude !== null);
+    const max_alt = Math.max(...altitudes);
+    const min_alt = Math.min(...altitudes);
 
     const lines = [
         `Tracking Stats:`,
@@ -74,7 +79,7 @@ function draw_aircraft_table(
     const rows = filtered.map(a => [
         a.icao.toString(16).toUpperCase(),
         a.callsign || "",
-        a.altitude.toString(),
+        a.altitude !== null ? a.altitude.toString() : "N/A",
         ((Date.now() - a.last_contact) / 1000).toFixed(1)
     ]);
     const tableData = [headers, ...rows];
@@ -171,6 +176,37 @@ function handle_new_aircraft(aircraftData: any, aircraft: Aircraft[], center: Ce
     }
 }
 
+/**
+ * Handle a lifecycle event from the aircraft tracker.
+ * 
+ * @param event the tracker event
+ * @param aircraft the current aircraft available
+ */
+function handle_tracker_event(event: TrackerEvent, aircraft: Aircraft[]) {
+    if (event.type === "Lost") {
+        const index = aircraft.findIndex(ac => ac.icao === event.icao);
+        if (index !== -1) {
+            aircraft.splice(index, 1);
+        }
+    }
+}
+
+/**
+ * Fetch the track history of an aircraft from the backend.
+ * 
+ * @param plane the aircraft to update the track of
+ */
+function update_track(plane: Aircraft) {
+    fetch(`/api/track/${plane.icao}`)
+        .then(response => response.ok ? response.json() : null)
+        .then((track: AircraftTrack | null) => {
+            plane.track = track
+                ? track.points.map(point => new Position(point.position.latitude, point.position.longitude))
+                : [];
+        })
+        .catch(() => { plane.track = []; });
+}
+
 class AircraftDisplayApp {
     private canvas: HTMLCanvasElement;
     private ctx: CanvasRenderingContext2D;
@@ -237,6 +273,9 @@ class AircraftDisplayApp {
             for (const ac of this.aircraft) {
                 if (ac.update_hover(mx, my)) {
                     ac.toggle_expanded();
+                    if (ac.is_expanded() && this.socket != null) {
+                        update_track(ac);
+                    }
                     return;
                 }
             }
@@ -245,7 +284,12 @@ class AircraftDisplayApp {
         if (this.socket != null) {
             this.socket.onmessage = (event) => {
                 const data = JSON.parse(event.data);
-                handle_new_aircraft(data, this.aircraft, this.center);
+                // Tracker events are tagged with a type, aircraft summaries are not
+                if (data.type !== undefined) {
+                    handle_tracker_event(data, this.aircraft);
+                } else {
+                    handle_new_aircraft(data, this.aircraft, this.center);
+                }
             };
         }
 
@@ -293,6 +337,10 @@ class AircraftDisplayApp {
         if ((timestamp - this.lastUpdate) >= CONFIG.UPDATE_RATE) {
             if (CONFIG.DEMO_MODE) {
                 update_aircraft_demo(this.aircraft);
+            } else {
+                this.aircraft
+                    .filter(plane => plane.is_expanded())
+                    .forEach(plane => update_track(plane));
             }
             this.lastUpdate = timestamp;
         }
@@ -301,7 +349,7 @@ class AircraftDisplayApp {
         this.aircraft.forEach(plane => {
             if (plane.pos != null) {
                 plane.update_pos_xy(this.center);
-                plane.draw(this.ctx);
+                plane.draw(this.ctx, this.center);
                 plane.update_hover(this.mouse.x, this.mouse.y);
             } else {
                 no_pos_aircraft.push(plane);
@@ -0,0 +1,62 @@
+/// Benchmarks for the parity check and error correction
+///
+/// The original bit by bit implementations are compared against.
+///
+/// Run with `cargo bench --bench crc`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/adsb/crc.rs"]
+mod crc;
+#[allow(dead_code)]
+#[path = "../src/adsb/fixtures.rs"]
+mod fixtures;
+
+/// The crc tests refer to the fixtures through the adsb module
+mod adsb {
+    pub(crate) use super::fixtures;
+}
+
+use crc::{get_adsb_crc, try_crc_recovery};
+use fixtures::{get_adsb_crc_bitwise, FRAME};
+
+/// The original recovery which recomputes the crc for every single bit flip
+fn try_crc_recovery_bitwise(buf: Vec<u8>) -> Option<Vec<u8>> {
+    for bit in 0..buf.len() * 8 {
+        let mut augmented_buf = buf.clone();
+        augmented_buf[bit / 8] ^= 1 << (7 - bit % 8);
+
+        if get_adsb_crc_bitwise(&augmented_buf) == 0 {
+            return Some(augmented_buf);
+        }
+    }
+
+    None
+}
+
+fn bench_crc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("crc");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("bitwise", |b| b.iter(|| get_adsb_crc_bitwise(black_box(&FRAME[0..11]))));
+    group.bench_function("table", |b| b.iter(|| get_adsb_crc(black_box(&FRAME[0..11]))));
+    group.finish();
+}
+
+fn bench_recovery(c: &mut Criterion) {
+    let mut one_bit = FRAME.to_vec();
+    one_bit[7] ^= 0x02;
+    let mut two_bit = one_bit.clone();
+    two_bit[10] ^= 0x40;
+    assert!(try_crc_recovery(one_bit.clone(), 1).is_some() && try_crc_recovery(two_bit.clone(), 2).is_some());
+
+    let mut group = c.benchmark_group("recovery");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("one_bit_bitwise", |b| b.iter(|| try_crc_recovery_bitwise(black_box(one_bit.clone()))));
+    group.bench_function("one_bit_table", |b| b.iter(|| try_crc_recovery(black_box(one_bit.clone()), 1)));
+    group.bench_function("two_bit_table", |b| b.iter(|| try_crc_recovery(black_box(two_bit.clone()), 2)));
+    group.finish();
+}
+
+criterion_group!(benches, bench_crc, bench_recovery);
+criterion_main!(benches);
@@ -0,0 +1,152 @@
+/// Benchmarks for the demodulator hot path
+///
+/// The binary has no library target so the modules the hot path needs are pulled in directly.
+/// The original implementations are kept here to compare against.
+///
+/// Run with `cargo bench --bench demod`
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use num_complex::Complex;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/adsb/crc.rs"]
+mod crc;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/adsb/demod.rs"]
+mod demod;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/cli.rs"]
+mod cli;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/utils.rs"]
+mod utils;
+#[allow(dead_code)]
+#[path = "../src/adsb/fixtures.rs"]
+mod fixtures;
+
+/// The demodulator refers to its neighbours through the adsb module
+mod adsb {
+    pub(crate) use super::{crc, fixtures};
+}
+
+use demod::{
+    check_for_adsb_packet, get_downlink_format, get_noise_power, DemodStats, FrameAction, StreamDemod, TwoSampleDemod,
+    PREAMBLE_SAMPLES, SUPPORTED_DOWNLINK_FORMATS,
+};
+use cli::ErrorCorrection;
+use utils::{get_magnitude, get_magnitude_i8};
+
+/// One second of samples at 2.4 MS/s
+const SAMPLES: usize = 2_400_000;
+
+/// Noise like samples that exercise the full range of the magnitude calculation
+fn get_samples() -> Vec<Complex<i16>> {
+    (0..SAMPLES as u32)
+        .map(|i| {
+            let seed = i.wrapping_mul(2_654_435_761);
+            Complex::new((seed as i16) >> 4, ((seed >> 16) as i16) >> 4)
+        })
+        .collect()
+}
+
+/// The original magnitude calculation in double precision
+fn get_magnitude_f64(buf: &[Complex<i16>]) -> Vec<u32> {
+    buf.iter()
+        .map(|c| ((c.re as f64).powi(2) + (c.im as f64).powi(2)).sqrt() as u32)
+        .collect()
+}
+
+/// The original preamble check which compared every high against every low
+fn check_for_adsb_packet_original(buf: [u32; 32]) -> Option<(u32, f64)> {
+    let lows = [1, 3, 4, 5, 6, 8, 10, 11, 12, 13, 14, 15];
+    let highs = [0, 2, 7, 9];
+    let mut min = u32::MAX;
+
+    for high in highs.iter() {
+        for low in lows.iter() {
+            if buf[*high as usize] < buf[*low as usize] {
+                return None;
+            }
+        }
+        if buf[*high as usize] < min {
+            min = buf[*high as usize];
+        }
+    }
+
+    if !SUPPORTED_DOWNLINK_FORMATS.contains(&get_downlink_format(&buf[PREAMBLE_SAMPLES..])) {
+        return None;
+    }
+
+    Some(((min as f32 * 0.9) as u32, get_noise_power(&buf[0..PREAMBLE_SAMPLES])))
+}
+
+/// The original scan which copied a window out for every sample
+fn scan_copy(mags: &[u32]) -> usize {
+    let mut found = 0;
+    for i in 0..mags.len() - 32 {
+        let check_mags: [u32; 32] = mags[i..i + 32].try_into().unwrap();
+        if check_for_adsb_packet_original(black_box(check_mags)).is_some() {
+            found += 1;
+        }
+    }
+
+    found
+}
+
+/// The scan borrowing each window from the magnitude buffer
+fn scan_zero_copy(mags: &[u32]) -> usize {
+    let mut found = 0;
+    for i in 0..mags.len() - 32 {
+        let check_mags: &[u32; 32] = mags[i..i + 32].try_into().unwrap();
+        if check_for_adsb_packet(black_box(check_mags)).is_some() {
+            found += 1;
+        }
+    }
+
+    found
+}
+
+fn bench_magnitude(c: &mut Criterion) {
+    let samples = get_samples();
+    let samples_i8: Vec<Complex<i8>> = samples.iter().map(|s| Complex::new((s.re >> 4) as i8, (s.im >> 4) as i8)).collect();
+
+    let mut group = c.benchmark_group("magnitude");
+    group.throughput(Throughput::Elements(SAMPLES as u64));
+    group.bench_function("f64", |b| b.iter(|| get_magnitude_f64(black_box(&samples))));
+    group.bench_function("i16", |b| b.iter(|| get_magnitude(black_box(&samples))));
+    group.bench_function("i8_table", |b| b.iter(|| get_magnitude_i8(black_box(&samples_i8))));
+    group.finish();
+}
+
+fn bench_preamble_scan(c: &mut Criterion) {
+    let mags = get_magnitude(&get_samples());
+
+    let mut group = c.benchmark_group("preamble_scan");
+    group.throughput(Throughput::Elements(SAMPLES as u64));
+    group.bench_function("copy", |b| b.iter(|| scan_copy(black_box(&mags))));
+    group.bench_function("zero_copy", |b| b.iter(|| scan_zero_copy(black_box(&mags))));
+    group.finish();
+}
+
+fn bench_stream(c: &mut Criterion) {
+    let samples = get_samples();
+
+    let mut group = c.benchmark_group("stream");
+    group.throughput(Throughput::Elements(SAMPLES as u64));
+    for chunk in [16_384, 262_144] {
+        group.bench_with_input(BenchmarkId::new("two_sample", chunk), &chunk, |b, chunk| {
+            b.iter(|| {
+                let mut stream = StreamDemod::new(Box::new(TwoSampleDemod));
+                let mut stats = DemodStats::default();
+                for (i, buf) in samples.chunks(*chunk).enumerate() {
+                    stream.process((i * chunk) as u64, &get_magnitude(buf), ErrorCorrection::OneBit, &mut stats, |_, _| FrameAction::Use);
+                }
+                stats.good
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_magnitude, bench_preamble_scan, bench_stream);
+criterion_main!(benches);
@@ -1,4 +1,11 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AddressType } from "./AddressType";
+import type { AutopilotModes } from "./AutopilotModes";
+import type { DataSource } from "./DataSource";
+import type { EmergencyState } from "./EmergencyState";
+import type { EmitterCategory } from "./EmitterCategory";
+import type { EnhancedSurveillance } from "./EnhancedSurveillance";
 import type { GeographicPosition } from "./GeographicPosition";
+import type { PositionIntegrity } from "./PositionIntegrity";
 
-export interface AircraftSummary { icao: number, callsign: string, altitude: number, geoPosition: GeographicPosition | null, lastContact: bigint, }
\ No newline at end of file
+export interface AircraftSummary { icao: number, addressType: AddressType, source: DataSource, callsign: string, category: EmitterCategory | null, adsb: boolean, capability: number | null, altitude: number | null, geometricAltitude: number | null, geoPosition: GeographicPosition | null, groundSpeed: number | null, track: number | null, verticalRate: number | null, onGround: boolean, squawk: string | null, emergency: EmergencyState | null, selectedAltitude: number | null, selectedHeading: number | null, baroSetting: number | null, autopilotModes: AutopilotModes | null, rejectedPositions: number, signalPower: number | null, integrity: PositionIntegrity, enhancedSurveillance: EnhancedSurveillance, lastContact: bigint, }
\ No newline at end of file
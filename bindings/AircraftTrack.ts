@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TrackPoint } from "./TrackPoint";
+
+export interface AircraftTrack { icao: number, points: Array<TrackPoint>, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type EmitterCategory = "NoInformation" | "Light" | "Small" | "Large" | "HighVortexLarge" | "Heavy" | "HighPerformance" | "Rotorcraft" | "Glider" | "LighterThanAir" | "Parachutist" | "Ultralight" | "Uav" | "SpaceVehicle" | "EmergencyVehicle" | "ServiceVehicle" | "PointObstacle" | "ClusterObstacle" | "LineObstacle" | "Reserved";
\ No newline at end of file
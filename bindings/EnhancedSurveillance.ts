@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface EnhancedSurveillance { callsign: string | null, mcpAltitude: number | null, fmsAltitude: number | null, baroSetting: number | null, roll: number | null, trueTrack: number | null, groundSpeed: number | null, trackRate: number | null, trueAirspeed: number | null, magneticHeading: number | null, indicatedAirspeed: number | null, mach: number | null, baroVerticalRate: number | null, inertialVerticalRate: number | null, windSpeed: number | null, windDirection: number | null, temperature: number | null, pressure: number | null, humidity: number | null, turbulence: number | null, }
\ No newline at end of file
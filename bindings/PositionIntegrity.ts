@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { HeadingReference } from "./HeadingReference";
+
+export interface PositionIntegrity { adsbVersion: number | null, nic: number | null, containmentRadius: number | null, nacP: number | null, sil: number | null, gva: number | null, headingReference: HeadingReference | null, tcasOperational: boolean, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { GeographicPosition } from "./GeographicPosition";
+
+export interface TrackPoint { time: bigint, position: GeographicPosition, altitude: number | null, groundSpeed: number | null, track: number | null, onGround: boolean, }
\ No newline at end of file
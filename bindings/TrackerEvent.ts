@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { GeographicPosition } from "./GeographicPosition";
+
+export type TrackerEvent = { "type": "NewAircraft", icao: number, } | { "type": "PositionAcquired", icao: number, position: GeographicPosition, } | { "type": "CallsignChanged", icao: number, previous: string | null, callsign: string, } | { "type": "Lost", icao: number, };
\ No newline at end of file
@@ -1,38 +1,58 @@
 /// Implementation for the adsb packet structure and handling
 
+use std::cell::Cell;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use std::sync::mpsc::{self, Sender, Receiver};
 
+use chrono::Local;
 use num_complex::Complex;
-use soapysdr::{Device, Direction};
+use soapysdr::{Device, Direction, ErrorCode, Format, StreamSample};
 
 mod aircraft;
 mod tui;
 mod msgs;
 mod packet;
 mod demod;
+mod correlator;
+mod pipeline;
 mod crc;
+mod commb;
 mod cpr;
+mod modeac;
 mod web;
+mod tracker;
+#[cfg(test)]
+mod fixtures;
 
-use packet::AdsbPacket;
+use packet::{AdsbPacket, IcaoFilter};
 
-use crate::cli::DisplayMode;
+use crate::cli::{AdsbArgs, DemodulatorType, DisplayMode, ErrorCorrection};
 use crate::sdr::get_sdr_args;
-use crate::utils::{get_magnitude, load_data};
+use crate::utils::{load_data, Sample};
 use crate::adsb::web::web_interface_thread;
+use crate::adsb::aircraft::{ReceiverConfig, TrackRetention};
+use crate::adsb::cpr::GeographicPosition;
+use crate::adsb::tracker::AircraftTracker;
+use crate::adsb::correlator::CorrelationDemod;
+use crate::adsb::demod::{DemodFrame, DemodStats, Demodulator, FrameAction, SampleBuffer, SampleClock, StreamDemod, TwoSampleDemod};
 
 const SDR_GAIN: f64 = 49.50;
 const SDR_CHANNEL: usize = 0;
+/// Time between printing the demodulator statistics in the stream and web modes
+const STATS_LOG_INTERVAL: Duration = Duration::from_secs(10);
+/// Longest wait for a packet before the stream mode removes silent aircraft
+const STREAM_PRUNE_INTERVAL: Duration = Duration::from_secs(1);
 
 /// Setup the sdr device with the correct values
 /// 
 /// device - the device number to use
+/// sample_rate - the sample rate in Hz
 /// 
 /// returns the sdr device for use
-fn setup_sdr(device: Option<u32>) -> Device {
+fn setup_sdr(device: Option<u32>, sample_rate: f64) -> Device {
     let args = get_sdr_args(device).expect("Couldn't get sdr args");
 
     let dev = Device::new(args).expect("Couldn't create sdr device");
@@ -41,47 +61,79 @@ fn setup_sdr(device: Option<u32>) -> Device {
 
     dev.set_frequency(Direction::Rx, SDR_CHANNEL, 1_090_000_000.0, ()).expect("Couldn't set frequency");
 
-    dev.set_sample_rate(Direction::Rx, SDR_CHANNEL, 2_000_000.0).expect("couldn't set sample rate");
-    println!("Set up sdr device to 1090MHz freq and 2MHz sample");
+    dev.set_sample_rate(Direction::Rx, SDR_CHANNEL, sample_rate).expect("couldn't set sample rate");
+    println!("Set up sdr device to 1090MHz freq and {}MHz sample", sample_rate / 1e6);
 
     dev
 }
 
+/// Get the wall clock time the first sample of a buffer was taken
+///
+/// len - the number of samples in the buffer just received
+/// sample_rate - the sample rate in Hz
+fn get_buffer_start(len: usize, sample_rate: f64) -> chrono::DateTime<Local> {
+    Local::now() - chrono::Duration::nanoseconds((len as f64 * 1e9 / sample_rate).round() as i64)
+}
+
 /// Get data from the sdr and then send it to a thread to process it
 /// 
 /// dev - the device to get data from
 /// tx - the tx handler to use to send the data
-fn get_sdr_data_thread(dev: Device, tx: Sender<Vec<Complex<i16>>>) {
-    let mut stream = dev.rx_stream::<Complex<i16>>(&[SDR_CHANNEL]).expect("Couldn't start stream");
+/// sample_rate - the sample rate in Hz
+/// clock - started when the first buffer arrives
+fn get_sdr_data_thread<T: Sample + StreamSample + Default>(dev: Device, tx: Sender<SampleBuffer<T>>, sample_rate: f64, clock: Arc<OnceLock<SampleClock>>) {
+    let mut stream = dev.rx_stream::<T>(&[SDR_CHANNEL]).expect("Couldn't start stream");
 
     stream.activate(None).expect("Couldn't activate stream");
 
-    let mut buf: Vec<Complex<i16>> = vec![Complex::new(0, 0); stream.mtu().expect("Couldn't get buf")];
+    let mut buf: Vec<T> = vec![T::default(); stream.mtu().expect("Couldn't get buf")];
+    let mut next_sample: u64 = 0;
 
     loop {
         match stream.read(&mut [&mut buf], 2_000_000) {
             Ok(len) => {
-                let buf = buf[0..len].to_vec();
+                clock.get_or_init(|| SampleClock::new(get_buffer_start(len, sample_rate), sample_rate));
+
+                let buf = SampleBuffer { first_sample: next_sample, samples: buf[0..len].to_vec() };
+                next_sample += len as u64;
                 if tx.send(buf).is_err() {
                     println!("Raw sdr receiver is dropped");
                     return;
                 }
             }
-            Err(_e) => continue,
+            Err(e) if e.code == ErrorCode::Timeout => continue,
+            Err(_e) => {
+                // Samples were lost so skip to the sample being taken now to keep the times
+                // right, the gap stops frames being joined across it
+                if let Some(clock) = clock.get() {
+                    next_sample = clock.get_sample(Local::now()).max(next_sample + 1);
+                }
+            },
         }
     }
 }
 
-fn playback_thread(tx: Sender<Vec<Complex<i16>>>, data: Vec<Complex<i16>>) {
+/// Send samples from a recording as if they came from the sdr
+/// 
+/// sample_rate - the sample rate of the recording in Hz
+/// paced - true to send the samples at twice the rate they were recorded
+/// clock - started when the first buffer is sent
+fn playback_thread(tx: Sender<SampleBuffer>, data: Vec<Complex<i16>>, sample_rate: f64, paced: bool, clock: Arc<OnceLock<SampleClock>>) {
+    clock.get_or_init(|| SampleClock::new(Local::now(), sample_rate));
+
     let mut i: usize = 0;
-    while i < data.len()-20000 {
-        let buf = data[i..i+20000].to_vec();
-        i += 20000;
+    while i < data.len() {
+        let end = (i + 20000).min(data.len());
+        let buf = SampleBuffer { first_sample: i as u64, samples: data[i..end].to_vec() };
+        let len = end - i;
+        i = end;
         if tx.send(buf).is_err() {
             println!("Raw sdr receiver is dropped");
             return;
         }
-        thread::sleep(Duration::from_secs_f64(1e4/2e6));
+        if paced {
+            thread::sleep(Duration::from_secs_f64(len as f64 / sample_rate / 2.0));
+        }
         
     }
 
@@ -89,78 +141,174 @@ fn playback_thread(tx: Sender<Vec<Complex<i16>>>, data: Vec<Complex<i16>>) {
 }
 
 /// Process incoming sdr data sending the result to the display queue
-fn process_sdr_data_thread(rx: Receiver<Vec<Complex<i16>>>, tx: Sender<AdsbPacket>) {
-    let mut num_good = 0;
-    let mut num_processed = 0;
-    while let Ok(buf) = rx.recv() {
-        let mags: Vec<u32> = get_magnitude(&buf); // Accepts &[Complex<i16>]
-        
-        for mut _i in 0..(mags.len() - (16 + 112 * 2)) {
-            let check_mags: [u32; 32] =  mags[_i.._i + 32]
-                            .try_into()
-                            .expect("Bad packet length passed to adsb checker");
-            
-            if let Some((high, _signal_power, _noise_power)) 
-                    = demod::check_for_adsb_packet(check_mags) {
-                num_processed += 1;
-                if let Some(packet_buf) = demod::extract_packet(mags[_i+16.._i+112*2+16].to_vec(), high) {
-                    let packet = AdsbPacket::new(packet_buf);
-                    if tx.send(packet).is_err() {
-                        println!("Adsb msg receiver is dropped");
-                        return;
-                    }
-                    num_good += 1;
-                    _i += 16 + 112 * 2;
-                }
+/// 
+/// demodulator - the demodulator used to find frames
+/// correction - the error correction policy for extended squitters
+/// workers - the number of threads to demodulate with
+/// clock - converts the sample a frame starts at into its timestamps, started by the source
+/// before it sends the first buffer
+/// stats - updated with the demodulator statistics as each buffer is processed
+fn process_sdr_data_thread<T: Sample>(rx: Receiver<SampleBuffer<T>>, tx: Sender<AdsbPacket>, demodulator: Box<dyn Demodulator + Send + Sync>, correction: ErrorCorrection, workers: usize, clock: Arc<OnceLock<SampleClock>>, stats: Arc<Mutex<DemodStats>>) {
+    let mut known_icaos = IcaoFilter::new();
+    let receiver_dropped = Cell::new(false);
+    let mut accept = |sample: u64, frame: DemodFrame| {
+        let clock = clock.get().expect("Sample clock is started before the first buffer");
+        let mut packet = AdsbPacket::new(frame.packet);
+        packet.corrected_bits = frame.corrected_bits;
+        packet.signal_power = Some(frame.signal_power);
+        packet.noise_power = Some(frame.noise_power);
+        packet.timestamp = Some(clock.get_ticks(sample));
+        packet.time_processed = clock.get_time(sample);
+        if !known_icaos.update(&packet) {
+            return FrameAction::Reject;
+        }
+
+        if tx.send(packet).is_err() {
+            receiver_dropped.set(true);
+            return FrameAction::Stop;
+        }
+        FrameAction::Use
+    };
+
+    if workers > 1 {
+        pipeline::process_parallel(rx, Arc::from(demodulator), correction, workers, &stats, accept);
+    } else {
+        let mut buffer_stats = DemodStats::default();
+        let mut stream = StreamDemod::new(demodulator);
+        while let Ok(buf) = rx.recv() {
+            let mags: Vec<u32> = T::get_magnitude(&buf.samples);
+
+            let running = stream.process(buf.first_sample, &mags, correction, &mut buffer_stats, &mut accept);
+            *stats.lock().expect("Demod stats poisoned") = buffer_stats.clone();
+            if !running {
+                break;
             }
         }
+    }
 
-        
+    if receiver_dropped.get() {
+        println!("Adsb msg receiver is dropped");
+        return;
     }
-    // println!("Processed: {}, Good: {}", num_processed, num_good);
     drop(tx);
 }
 
+/// Print every packet and the aircraft lifecycle events followed by the demodulator
+/// statistics at a regular interval and when the stream ends
+///
+/// rx - the receiver for ADS-B packets
+/// tracker - the tracker holding the aircraft
+/// stats - the demodulator statistics
+fn stream_display_thread(rx: Receiver<AdsbPacket>, mut tracker: AircraftTracker, stats: Arc<Mutex<DemodStats>>) {
+    let events = tracker.subscribe();
+    let mut last_log = Instant::now();
+
+    loop {
+        match rx.recv_timeout(STREAM_PRUNE_INTERVAL) {
+            Ok(packet) => {
+                print!("\n{}\n", packet);
+                tracker.handle_packet(packet);
+            },
+            Err(mpsc::RecvTimeoutError::Timeout) => (),
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        tracker.prune(Instant::now());
+
+        while let Ok(event) = events.try_recv() {
+            println!("{}", event);
+        }
+
+        if last_log.elapsed() >= STATS_LOG_INTERVAL {
+            println!("\nTracking {} aircraft", tracker.len());
+            print!("{}", stats.lock().expect("Demod stats poisoned"));
+            last_log = Instant::now();
+        }
+    }
+
+    println!("\nTracking {} aircraft", tracker.len());
+    print!("{}", stats.lock().expect("Demod stats poisoned"));
+}
+
+/// Start reading samples from the sdr in its native format and processing them
+///
+/// dev - the device to read from
+/// tx - the tx handler to send the packets found to
+/// demodulator - the demodulator used to find frames
+/// correction - the error correction policy for extended squitters
+/// workers - the number of threads to demodulate with
+/// sample_rate - the sample rate in Hz
+/// stats - updated with the demodulator statistics
+fn start_sdr_threads<T: Sample + StreamSample + Default>(dev: Device, tx: Sender<AdsbPacket>, demodulator: Box<dyn Demodulator + Send + Sync>, correction: ErrorCorrection, workers: usize, sample_rate: f64, stats: Arc<Mutex<DemodStats>>) {
+    let (tx_raw_sdr, rx_raw_sdr): (Sender<SampleBuffer<T>>, Receiver<SampleBuffer<T>>) = mpsc::channel();
+    let clock = Arc::new(OnceLock::new());
+    let sdr_clock = Arc::clone(&clock);
+
+    thread::spawn(move || {get_sdr_data_thread(dev, tx_raw_sdr, sample_rate, sdr_clock);});
+    thread::spawn(move || {process_sdr_data_thread(rx_raw_sdr, tx, demodulator, correction, workers, clock, stats);});
+}
+
 
 
-pub fn launch_adsb(device: Option<u32>, mode: DisplayMode, playback: Option<String>) {
+pub fn launch_adsb(device: Option<u32>, mode: DisplayMode, playback: Option<String>, args: AdsbArgs) {
     println!("Launching adsb with device: {:?}", device);
+
+    let position = match (args.latitude, args.longitude) {
+        (Some(latitude), Some(longitude)) => Some(GeographicPosition { latitude, longitude }),
+        _ => None,
+    };
+    let track_retention = TrackRetention { max_age_s: args.track_history as i64, max_points: args.track_points };
+    let config = ReceiverConfig { position, max_range_nm: Some(args.max_range), track_retention };
+    let tracker = AircraftTracker::new(config, args.aircraft_timeout as i64);
     // Find RTL-SDR device
     
 
-    let (tx_raw_sdr, rx_raw_sdr): (Sender<Vec<Complex<i16>>>, Receiver<Vec<Complex<i16>>>) = mpsc::channel();
-    let _stream_thread;
+    let sample_rate = args.sample_rate;
+    let paced = !args.fast_playback;
+    let demodulator: Box<dyn Demodulator + Send + Sync> = match args.get_demodulator().expect("Demodulator is checked by the cli") {
+        DemodulatorType::TwoSample => Box::new(TwoSampleDemod),
+        DemodulatorType::Correlation => Box::new(CorrelationDemod::new(sample_rate)),
+    };
+
+    let (tx_adsb_msgs, rx_adsb_msgs):(Sender<AdsbPacket>, Receiver<AdsbPacket>) = mpsc::channel();
+    let correction = args.error_correction;
+    let workers = args.workers as usize;
+    let stats = Arc::new(Mutex::new(DemodStats::default()));
+    let process_stats = Arc::clone(&stats);
+
+    // Frames are timed from the first sample so the clock starts when the first buffer arrives
     if playback.is_some() {
         println!("Starting data load from playback file: {}", playback.as_ref().unwrap());
         let data = load_data(playback.unwrap()).expect("Couldn't load playback data file");
         println!("Loaded {} samples from playback file", data.len());
         
-        _stream_thread = thread::spawn(move || {
-            playback_thread(tx_raw_sdr, data);
+        let (tx_raw_sdr, rx_raw_sdr): (Sender<SampleBuffer>, Receiver<SampleBuffer>) = mpsc::channel();
+        let clock = Arc::new(OnceLock::new());
+        let playback_clock = Arc::clone(&clock);
+        let _stream_thread = thread::spawn(move || {
+            playback_thread(tx_raw_sdr, data, sample_rate, paced, playback_clock);
         });
+        let _process_thread = thread::spawn(move || {process_sdr_data_thread(rx_raw_sdr, tx_adsb_msgs, demodulator, correction, workers, clock, process_stats);});
     } else {
-        let dev = setup_sdr(device);
-        _stream_thread = thread::spawn(move || {get_sdr_data_thread(dev, tx_raw_sdr);});
-    }
+        let dev = setup_sdr(device, sample_rate);
 
-    let (tx_adsb_msgs, rx_adsb_msgs):(Sender<AdsbPacket>, Receiver<AdsbPacket>) = mpsc::channel();
-    let _process_thread = thread::spawn(move || {process_sdr_data_thread(rx_raw_sdr, tx_adsb_msgs);});
+        // 8 bit devices such as the RTL-SDR are read as they are to use the magnitude table
+        match dev.native_stream_format(Direction::Rx, SDR_CHANNEL) {
+            Ok((Format::CS8, _)) => start_sdr_threads::<Complex<i8>>(dev, tx_adsb_msgs, demodulator, correction, workers, sample_rate, process_stats),
+            _ => start_sdr_threads::<Complex<i16>>(dev, tx_adsb_msgs, demodulator, correction, workers, sample_rate, process_stats),
+        }
+    }
 
     let display_thread;
     match mode {
         DisplayMode::Interactive => {
-            display_thread = thread::spawn(move || {tui::interactive_display_thread_tui(rx_adsb_msgs);});
+            display_thread = thread::spawn(move || {tui::interactive_display_thread_tui(rx_adsb_msgs, tracker, stats);});
         },
         DisplayMode::Stream => {
-            display_thread = thread::spawn(move || {
-                while let Ok(packet) = rx_adsb_msgs.recv() {
-                    print!("\n{}\n", packet);
-                }
-            });
+            display_thread = thread::spawn(move || {stream_display_thread(rx_adsb_msgs, tracker, stats);});
         }
         DisplayMode::Web => {
             display_thread = thread::spawn(move || {
-                web_interface_thread(rx_adsb_msgs);
+                web_interface_thread(rx_adsb_msgs, tracker, stats);
             });
             
         }
@@ -2,13 +2,17 @@
 /// 
 /// Author: Jack Duignan (JackpDuignan@gmail.com)
 
+use std::collections::VecDeque;
+
 use chrono::{DateTime, Local, Utc};
 use serde::Serialize;
 use ts_rs::TS;
 
-use crate::adsb::msgs::{AdsbMsgType, CprFormat};
+use crate::adsb::msgs::{AdsbMsgType, AltitudeSource, AutopilotModes, CprFormat, EmergencyState, EmitterCategory, HeadingReference, OperationalStatus};
 use crate::adsb::{self, cpr};
-use crate::adsb::cpr::{calculate_geographic_position, GeographicPosition};
+use crate::adsb::commb::CommB;
+use crate::adsb::packet::{format_address, AddressType, DataSource};
+use crate::adsb::cpr::{calculate_geographic_position, calculate_local_position, calculate_surface_position, GeographicPosition};
 
 /// Summary of only aircraft information that is needed for displaying aircraft
 #[derive(Debug, Clone, Serialize, TS)]
@@ -16,33 +20,368 @@ use crate::adsb::cpr::{calculate_geographic_position, GeographicPosition};
 #[serde(rename_all = "camelCase")]
 pub struct AircraftSummary {
     icao: u32,
+    address_type: AddressType,
+    source: DataSource,
     callsign: String,
-    altitude: i32,
+    category: Option<EmitterCategory>,
+    /// True if ADS-B has been received, false for Mode S only aircraft
+    adsb: bool,
+    capability: Option<u8>,
+    altitude: Option<i32>,
+    geometric_altitude: Option<i32>,
     geo_position: Option<GeographicPosition>,
+    ground_speed: Option<f64>,
+    track: Option<f64>,
+    vertical_rate: Option<i32>,
+    on_ground: bool,
+    /// Mode A code as four octal digits
+    squawk: Option<String>,
+    emergency: Option<EmergencyState>,
+    selected_altitude: Option<i32>,
+    selected_heading: Option<f64>,
+    baro_setting: Option<f64>,
+    autopilot_modes: Option<AutopilotModes>,
+    rejected_positions: u32,
+    /// Average signal power of recent frames in dBFS
+    signal_power: Option<f64>,
+    integrity: PositionIntegrity,
+    enhanced_surveillance: EnhancedSurveillance,
     last_contact: i64,
 }
 
+/// Integrity and accuracy of the reported position
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionIntegrity {
+    adsb_version: Option<u8>,
+    /// Navigation integrity category
+    nic: Option<u8>,
+    /// Containment radius in metres
+    containment_radius: Option<f64>,
+    /// Navigation accuracy category for position
+    nac_p: Option<u8>,
+    /// Source integrity level
+    sil: Option<u8>,
+    /// Geometric vertical accuracy
+    gva: Option<u8>,
+    heading_reference: Option<HeadingReference>,
+    tcas_operational: bool,
+}
+
+/// Aircraft state reported in Comm-B replies to radar interrogations,
+/// kept separate from the ADS-B state as it comes from different sensors
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct EnhancedSurveillance {
+    callsign: Option<String>,
+    /// MCP/FCU selected altitude in feet
+    mcp_altitude: Option<i32>,
+    /// FMS selected altitude in feet
+    fms_altitude: Option<i32>,
+    /// Barometric pressure setting in millibars
+    baro_setting: Option<f64>,
+    /// Roll angle in degrees, negative for left wing down
+    roll: Option<f64>,
+    /// True track in degrees
+    true_track: Option<f64>,
+    /// Ground speed in knots
+    ground_speed: Option<f64>,
+    /// Track angle rate in degrees per second
+    track_rate: Option<f64>,
+    /// True airspeed in knots
+    true_airspeed: Option<f64>,
+    /// Magnetic heading in degrees
+    magnetic_heading: Option<f64>,
+    /// Indicated airspeed in knots
+    indicated_airspeed: Option<f64>,
+    mach: Option<f64>,
+    /// Barometric altitude rate in feet per minute
+    baro_vertical_rate: Option<i32>,
+    /// Inertial vertical rate in feet per minute
+    inertial_vertical_rate: Option<i32>,
+    /// Wind speed in knots
+    wind_speed: Option<f64>,
+    /// Wind direction in degrees
+    wind_direction: Option<f64>,
+    /// Static air temperature in degrees celsius
+    temperature: Option<f64>,
+    /// Average static pressure in hectopascals
+    pressure: Option<f64>,
+    /// Relative humidity in percent
+    humidity: Option<f64>,
+    /// Turbulence level from 0 (nil) to 3 (severe)
+    turbulence: Option<u8>,
+}
+
+impl EnhancedSurveillance {
+    /// Update the state from a decoded Comm-B register, fields not reported are kept
+    ///
+    /// `register` - the decoded register
+    fn update(&mut self, register: CommB) {
+        match register {
+            CommB::Identification(callsign) => self.callsign = Some(callsign),
+            CommB::VerticalIntention(intention) => {
+                self.mcp_altitude = intention.mcp_altitude.or(self.mcp_altitude);
+                self.fms_altitude = intention.fms_altitude.or(self.fms_altitude);
+                self.baro_setting = intention.baro_setting.or(self.baro_setting);
+            }
+            CommB::MeteorologicalRoutine(met) => {
+                self.wind_speed = met.wind_speed.or(self.wind_speed);
+                self.wind_direction = met.wind_direction.or(self.wind_direction);
+                self.temperature = Some(met.temperature);
+                self.pressure = met.pressure.or(self.pressure);
+                self.humidity = met.humidity.or(self.humidity);
+                self.turbulence = met.turbulence.or(self.turbulence);
+            }
+            CommB::MeteorologicalHazard(hazard) => {
+                self.temperature = hazard.temperature.or(self.temperature);
+                self.pressure = hazard.pressure.or(self.pressure);
+                self.turbulence = hazard.turbulence.or(self.turbulence);
+            }
+            CommB::TrackTurn(track) => {
+                self.roll = track.roll.or(self.roll);
+                self.true_track = track.true_track.or(self.true_track);
+                self.ground_speed = track.ground_speed.or(self.ground_speed);
+                self.track_rate = track.track_rate.or(self.track_rate);
+                self.true_airspeed = track.true_airspeed.or(self.true_airspeed);
+            }
+            CommB::HeadingSpeed(heading) => {
+                self.magnetic_heading = heading.magnetic_heading.or(self.magnetic_heading);
+                self.indicated_airspeed = heading.indicated_airspeed.or(self.indicated_airspeed);
+                self.mach = heading.mach.or(self.mach);
+                self.baro_vertical_rate = heading.baro_vertical_rate.or(self.baro_vertical_rate);
+                self.inertial_vertical_rate = heading.inertial_vertical_rate.or(self.inertial_vertical_rate);
+            }
+        }
+    }
+}
+
+/// A position in the track history of an aircraft
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackPoint {
+    /// Unix time the position was received in milliseconds
+    time: i64,
+    position: GeographicPosition,
+    /// Barometric altitude in feet
+    altitude: Option<i32>,
+    /// Ground speed in knots
+    ground_speed: Option<f64>,
+    /// Track angle in degrees
+    track: Option<f64>,
+    on_ground: bool,
+}
+
+/// Recent positions of an aircraft used to draw its trail
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct AircraftTrack {
+    icao: u32,
+    /// Positions from oldest to newest with points along straight segments removed
+    points: Vec<TrackPoint>,
+}
+
+/// Limits on the track history kept for each aircraft
+#[derive(Debug, Clone)]
+pub struct TrackRetention {
+    /// Positions older than this relative to the newest position are dropped (seconds)
+    pub max_age_s: i64,
+    /// Maximum number of positions kept
+    pub max_points: usize,
+}
+
+impl Default for TrackRetention {
+    fn default() -> Self {
+        TrackRetention { max_age_s: DEFAULT_TRACK_AGE_S, max_points: DEFAULT_TRACK_POINTS }
+    }
+}
+
+/// Settings of the receiver used when decoding aircraft information
+#[derive(Debug, Clone, Default)]
+pub struct ReceiverConfig {
+    /// Location of the receiver used as a reference when resolving positions
+    pub position: Option<GeographicPosition>,
+    /// Positions further than this from the receiver are rejected (nautical miles)
+    pub max_range_nm: Option<f64>,
+    /// How much of the track history is kept
+    pub track_retention: TrackRetention,
+}
+
+/// Maximum time between airborne CPR frames for them to be paired
+const AIRBORNE_CPR_WINDOW_S: i64 = 10;
+/// Maximum time between surface CPR frames for them to be paired
+const SURFACE_CPR_WINDOW_S: i64 = 25;
+/// Even and odd CPR positions with the format of the older frame
+type CprPair = ((u32, u32), (u32, u32), CprFormat);
+
+/// Maximum age of a position for it to be used as a reference for local CPR decoding
+const LOCAL_REFERENCE_TIMEOUT_S: i64 = 60;
+/// Maximum range for the receiver to be used as an airborne local CPR reference (half a zone)
+const AIRBORNE_RECEIVER_REFERENCE_NM: f64 = 180.0;
+/// Maximum range for the receiver to be used as a surface local CPR reference (half a zone)
+const SURFACE_RECEIVER_REFERENCE_NM: f64 = 45.0;
+/// Maximum believable airborne speed in knots
+const MAX_AIRBORNE_SPEED_KT: f64 = 1000.0;
+/// Maximum believable surface speed in knots
+const MAX_SURFACE_SPEED_KT: f64 = 200.0;
+/// Number of recent frames averaged for the signal power
+const SIGNAL_HISTORY_LEN: usize = 8;
+/// Maximum distance between a global and local decode of the same frame
+const MAX_CPR_DISAGREEMENT_NM: f64 = 1.0;
+/// Default age of the oldest position kept in the track history
+const DEFAULT_TRACK_AGE_S: i64 = 900;
+/// Default number of positions kept in the track history
+const DEFAULT_TRACK_POINTS: usize = 1000;
+/// Maximum distance of a track point from the straight line between its neighbours for it to be removed
+const TRACK_THINNING_OFFSET_NM: f64 = 0.05;
+/// Maximum difference between the altitude of a track point and its neighbours' trend for it to be removed
+const TRACK_THINNING_ALTITUDE_FT: i32 = 100;
+/// Maximum time between kept track points so the trail can still be timed along straight segments
+const TRACK_THINNING_MAX_GAP_MS: i64 = 60_000;
+
+/// Check an aircraft could travel between two positions in the time between them
+///
+/// `previous` - the earlier position
+/// `previous_time` - the time of the earlier position
+/// `position` - the later position
+/// `time` - the time of the later position
+/// `on_ground` - true to use the surface speed limit
+fn is_reachable(previous: &GeographicPosition, previous_time: DateTime<Local>, position: &GeographicPosition, time: DateTime<Local>, on_ground: bool) -> bool {
+    let max_speed = if on_ground { MAX_SURFACE_SPEED_KT } else { MAX_AIRBORNE_SPEED_KT };
+    let elapsed = (time - previous_time).num_milliseconds().abs() as f64 / 1000.0;
+
+    // Allow an extra second of travel to cover timing jitter
+    previous.distance_nm(position) <= max_speed * (elapsed + 1.0) / 3600.0
+}
+
+/// Check if a track point can be removed without changing the shape of the trail
+///
+/// `previous` - the point before
+/// `point` - the point to check
+/// `next` - the point after
+///
+/// returns true if the point lies on a straight and level segment between its neighbours
+fn is_straight_segment(previous: &TrackPoint, point: &TrackPoint, next: &TrackPoint) -> bool {
+    if previous.on_ground != point.on_ground || point.on_ground != next.on_ground {
+        return false;
+    }
+
+    if next.time - previous.time > TRACK_THINNING_MAX_GAP_MS {
+        return false;
+    }
+
+    // The point must be between its neighbours not beyond them after a reversal
+    if previous.position.distance_nm(&point.position) > previous.position.distance_nm(&next.position) {
+        return false;
+    }
+
+    if point.position.cross_track_nm(&previous.position, &next.position) > TRACK_THINNING_OFFSET_NM {
+        return false;
+    }
+
+    match (previous.altitude, point.altitude, next.altitude) {
+        (Some(start), Some(altitude), Some(end)) => {
+            let span = (next.time - previous.time).max(1) as f64;
+            let fraction = (point.time - previous.time) as f64 / span;
+            let expected = start as f64 + (end - start) as f64 * fraction;
+
+            (altitude as f64 - expected).abs() <= TRACK_THINNING_ALTITUDE_FT as f64
+        },
+        (start, altitude, end) => start == altitude && altitude == end,
+    }
+}
+
 /// Holder for aircraft information that has been received from adsb
 #[derive(Debug, Clone)]
 pub struct Aircraft {
     icao: u32,
     callsign: Option<String>,
-    altitude: i32,
+    category: Option<EmitterCategory>,
+    /// True once an extended squitter has been received
+    adsb: bool,
+    /// Source of the last extended squitter or Mode S if none have been received
+    source: DataSource,
+    /// Transponder capability from all call replies
+    capability: Option<u8>,
+    /// Barometric altitude in feet
+    altitude: Option<i32>,
+    /// GNSS altitude in feet
+    geometric_altitude: Option<i32>,
     geo_position: Option<GeographicPosition>,
+    /// Time the position was last updated
+    position_time: Option<DateTime<Local>>,
+    /// True once a second global decode has agreed with the position
+    position_confirmed: bool,
+    /// True if the position was only decoded locally against the receiver so later positions aren't checked against it
+    position_from_receiver: bool,
+    /// The last global decode that disagreed with an unconfirmed position and when it was received
+    unconfirmed_position: Option<(GeographicPosition, DateTime<Local>)>,
+    /// Ground speed in knots
+    ground_speed: Option<f64>,
+    /// Track angle in degrees clockwise from true north
+    track: Option<f64>,
+    /// Vertical rate in feet per minute
+    vertical_rate: Option<i32>,
+    on_ground: bool,
+    /// Mode A code with each octal digit in 3 bits
+    squawk: Option<u16>,
+    emergency: Option<EmergencyState>,
+    /// Autopilot selected altitude in feet
+    selected_altitude: Option<i32>,
+    /// Autopilot selected heading in degrees
+    selected_heading: Option<f64>,
+    /// Barometric pressure setting in millibars
+    baro_setting: Option<f64>,
+    autopilot_modes: Option<AutopilotModes>,
+    /// Number of positions that failed the reasonableness checks
+    rejected_positions: u32,
+    /// Signal power of the most recent frames in dBFS
+    signal_powers: VecDeque<f64>,
+    /// Latest operational status message
+    operational_status: Option<OperationalStatus>,
+    /// State reported in Comm-B replies
+    enhanced_surveillance: EnhancedSurveillance,
+    /// Navigation integrity category of the last position
+    nic: Option<u8>,
+    /// Containment radius of the last position in metres
+    containment_radius: Option<f64>,
+    /// Accepted positions from oldest to newest
+    track_history: VecDeque<TrackPoint>,
+    /// Positions dropped from the newest segment of the track history, checked again as it grows
+    thinned_points: Vec<TrackPoint>,
     last_contact: chrono::prelude::DateTime<Local>,
-    last_odd_packet: Option<adsb::msgs::AircraftPosition>,
+    last_odd_cpr: Option<(u32, u32)>,
     last_odd_processed: chrono::prelude::DateTime<Local>,
-    last_even_packet: Option<adsb::msgs::AircraftPosition>,
+    last_even_cpr: Option<(u32, u32)>,
     last_even_processed: chrono::prelude::DateTime<Local>,
+    config: ReceiverConfig,
 }
 
 impl Aircraft {
+    #[cfg(test)]
     pub fn new(icao: u32) -> Self {
-        Aircraft { icao: icao, callsign: None, 
-        altitude: 0, geo_position: None,
+        Aircraft::with_config(icao, ReceiverConfig::default())
+    }
+
+    /// Create a new aircraft that uses the given receiver settings
+    pub fn with_config(icao: u32, config: ReceiverConfig) -> Self {
+        Aircraft { icao: icao, callsign: None, category: None, adsb: false, source: DataSource::ModeS, capability: None,
+        altitude: None, geometric_altitude: None, geo_position: None, position_time: None,
+        position_confirmed: false, position_from_receiver: false, unconfirmed_position: None,
+        ground_speed: None, track: None, vertical_rate: None,
+        on_ground: false, squawk: None, emergency: None,
+        selected_altitude: None, selected_heading: None, baro_setting: None, autopilot_modes: None,
+        rejected_positions: 0, signal_powers: VecDeque::with_capacity(SIGNAL_HISTORY_LEN),
+        operational_status: None, enhanced_surveillance: EnhancedSurveillance::default(),
+        nic: None, containment_radius: None, track_history: VecDeque::new(), thinned_points: Vec::new(),
         last_contact: Local::now(), 
-        last_odd_packet: None, last_even_packet: None,
-        last_odd_processed: Local::now(), last_even_processed: Local::now() } 
+        last_odd_cpr: None, last_even_cpr: None,
+        last_odd_processed: Local::now(), last_even_processed: Local::now(),
+        config: config } 
         }
 
     pub fn handle_packet(&mut self, msg: adsb::AdsbPacket) {
@@ -50,59 +389,118 @@ impl Aircraft {
             return;
         }
 
+        if let Some(signal_power) = msg.signal_power {
+            if self.signal_powers.len() == SIGNAL_HISTORY_LEN {
+                self.signal_powers.pop_front();
+            }
+            self.signal_powers.push_back(signal_power);
+        }
+
+        if msg.is_extended_squitter() {
+            self.adsb = true;
+            self.source = msg.source;
+        }
+
         match msg.msg {
             AdsbMsgType::AircraftPosition(ref pos) => {
-                self.altitude = pos.get_altitude_ft();
+                if pos.get_altitude_ft().is_some() {
+                    match pos.get_altitude_source() {
+                        AltitudeSource::Barometric => self.altitude = pos.get_altitude_ft(),
+                        AltitudeSource::Gnss => self.geometric_altitude = pos.get_altitude_ft(),
+                    }
+                }
+                self.last_contact = msg.time_processed;
+                self.set_on_ground(false);
+
+                let (nic, containment_radius) = pos.get_nic(self.get_nic_supplements().0);
+                self.nic = Some(nic);
+                self.containment_radius = containment_radius;
+
+                self.update_position(pos.get_cpr_format(), pos.get_cpr_position(), msg.time_processed);
+            }
+            AdsbMsgType::SurfacePosition(ref pos) => {
                 self.last_contact = msg.time_processed;
+                self.set_on_ground(true);
 
-                let cpr_odd;
-                let cpr_even;
-                let first;
-                
-                match pos.get_cpr_format() {
-                    adsb::msgs::CprFormat::Even => {
-                        self.last_even_packet = Some(pos.clone());
-                        self.last_even_processed = msg.time_processed;
-
-                        if let Some(odd_pos) = &self.last_odd_packet {
-                            if (msg.time_processed - self.last_odd_processed).abs() > chrono::Duration::seconds(10) {
-                                return;
-                            }
-
-                            cpr_odd = odd_pos.get_cpr_position();
-                            cpr_even = pos.get_cpr_position();
-                            first = CprFormat::Odd;
-                        } else {
-                            return;
-                        }
-                    },
-                    adsb::msgs::CprFormat::Odd => {
-                        self.last_odd_packet = Some(pos.clone());
-                        self.last_odd_processed = msg.time_processed;
-
-                        if let Some(even_pos) = &self.last_even_packet {
-                            if (msg.time_processed - self.last_even_processed).abs() > chrono::Duration::seconds(10) {
-                                return
-                            }    
-
-                            cpr_odd = pos.get_cpr_position();
-                            cpr_even = even_pos.get_cpr_position();
-                            first = CprFormat::Even;
-                        }  else {
-                            return;
-                        }
-                    },
+                let (nic_a, nic_c) = self.get_nic_supplements();
+                let (nic, containment_radius) = pos.get_nic(nic_a, nic_c);
+                self.nic = Some(nic);
+                self.containment_radius = containment_radius;
+
+                if let Some(ground_speed) = pos.get_ground_speed() {
+                    self.ground_speed = Some(ground_speed);
+                }
+
+                if let Some(track) = pos.get_ground_track() {
+                    self.track = Some(track);
                 }
 
-                if let Some(geo_position) = calculate_geographic_position(
-                                                                cpr_even,
-                                                                 cpr_odd, first) {
-                    self.geo_position = Some(geo_position);
-                };
-                
+                self.update_position(pos.get_cpr_format(), pos.get_cpr_position(), msg.time_processed);
             }
             AdsbMsgType::AircraftID(id) => {
                 self.callsign = Some(id.get_callsign());
+                self.category = Some(id.get_category());
+            }
+            AdsbMsgType::AirborneVelocity(vel) => {
+                self.last_contact = msg.time_processed;
+
+                if let Some(ground_speed) = vel.get_ground_speed() {
+                    self.ground_speed = Some(ground_speed);
+                    self.track = vel.get_track();
+                }
+
+                if vel.get_vertical_rate().is_some() {
+                    self.vertical_rate = vel.get_vertical_rate();
+                }
+
+                if let (Some(altitude), Some(difference)) = (self.altitude, vel.gnss_baro_diff) {
+                    self.geometric_altitude = Some(altitude + difference);
+                }
+            }
+            AdsbMsgType::OperationalStatus(status) => {
+                self.last_contact = msg.time_processed;
+                self.operational_status = Some(status);
+            }
+            AdsbMsgType::AircraftStatus(status) => {
+                self.last_contact = msg.time_processed;
+
+                if status.squawk.is_some() {
+                    self.squawk = status.squawk;
+                    self.emergency = status.emergency;
+                }
+            }
+            AdsbMsgType::TargetStateStatus(state) => {
+                self.last_contact = msg.time_processed;
+
+                if state.sub_type == 1 {
+                    self.selected_altitude = state.selected_altitude;
+                    self.selected_heading = state.selected_heading;
+                    self.baro_setting = state.baro_setting;
+                    self.autopilot_modes = state.autopilot_modes;
+                }
+            }
+            AdsbMsgType::AllCallReply(reply) => {
+                self.last_contact = msg.time_processed;
+                self.capability = Some(reply.capability);
+            }
+            AdsbMsgType::SurveillanceReply(reply) => {
+                self.last_contact = msg.time_processed;
+
+                if reply.altitude.is_some() {
+                    self.altitude = reply.altitude;
+                }
+
+                if reply.squawk.is_some() {
+                    self.squawk = reply.squawk;
+                }
+
+                if let Some(on_ground) = reply.on_ground {
+                    self.set_on_ground(on_ground);
+                }
+
+                if let Some(comm_b) = reply.comm_b {
+                    self.enhanced_surveillance.update(comm_b);
+                }
             }
             AdsbMsgType::Uknown(_unkown) => {
                 return;
@@ -110,60 +508,520 @@ impl Aircraft {
         }
     }
 
+    /// Update the position from a CPR frame
+    ///
+    /// A global decode is used when an even and odd pair is available otherwise the
+    /// frame is decoded locally against the last confirmed position or the receiver location
+    /// if the maximum range keeps aircraft within half a zone of it.
+    /// A position is only confirmed, and used as a local reference, once a second global
+    /// decode agrees with it. Positions that fail the reasonableness checks are rejected and counted.
+    ///
+    /// `format` - the format of the new frame
+    /// `cpr_position` - the raw CPR latitude and longitude of the new frame
+    /// `time` - the time the new frame was received
+    fn update_position(&mut self, format: CprFormat, cpr_position: (u32, u32), time: DateTime<Local>) {
+        let reference_position = self.get_reference_position(time);
+        let reference = reference_position.clone().or(self.config.position.clone());
+
+        let global_position = match self.pair_cpr_frame(format, cpr_position, time) {
+            Some((cpr_even, cpr_odd, first)) if self.on_ground => reference.as_ref()
+                .and_then(|reference| calculate_surface_position(cpr_even, cpr_odd, first, reference)),
+            Some((cpr_even, cpr_odd, first)) => calculate_geographic_position(cpr_even, cpr_odd, first),
+            None => None,
+        };
+
+        match global_position {
+            Some(global_position) => self.update_global_position(global_position, reference_position, format, cpr_position, time),
+            None => {
+                let from_receiver = reference_position.is_none();
+                let local_position = self.get_local_reference(reference_position, time)
+                    .and_then(|reference| calculate_local_position(cpr_position, format, &reference, self.on_ground));
+
+                if let Some(local_position) = local_position {
+                    if !self.is_position_reasonable(&local_position, time) {
+                        self.reject_position();
+                        return;
+                    }
+
+                    self.set_position(local_position, time);
+                    self.position_from_receiver = from_receiver;
+                }
+            },
+        }
+    }
+
+    /// Update the position from a global decode confirming it against the previous fix
+    ///
+    /// `global_position` - the globally decoded position
+    /// `reference_position` - the confirmed position the frame can be decoded locally against
+    /// `format` - the format of the new frame
+    /// `cpr_position` - the raw CPR latitude and longitude of the new frame
+    /// `time` - the time the new frame was received
+    fn update_global_position(&mut self, global_position: GeographicPosition, reference_position: Option<GeographicPosition>, format: CprFormat, cpr_position: (u32, u32), time: DateTime<Local>) {
+        if !self.is_in_range(&global_position) {
+            self.reject_position();
+            return;
+        }
+
+        if let Some(reference_position) = reference_position {
+            // A global decode must agree with a local decode against the confirmed fix
+            let local_position = calculate_local_position(cpr_position, format, &reference_position, self.on_ground);
+
+            if local_position.is_some_and(|local| global_position.distance_nm(&local) > MAX_CPR_DISAGREEMENT_NM) {
+                // Either could be wrong so the fix is no longer trusted as a reference
+                self.rejected_positions += 1;
+                self.set_unconfirmed_position(global_position, time);
+                return;
+            }
+        }
+
+        if self.get_recent_position(time).is_none() {
+            self.set_unconfirmed_position(global_position, time);
+            return;
+        }
+
+        if self.is_position_reasonable(&global_position, time) {
+            self.position_confirmed = true;
+            self.unconfirmed_position = None;
+            self.set_position(global_position, time);
+            return;
+        }
+
+        // Two global decodes that agree with each other but not the unconfirmed fix replace it
+        let agrees = self.unconfirmed_position.as_ref().is_some_and(|(candidate, candidate_time)| {
+            is_reachable(candidate, *candidate_time, &global_position, time, self.on_ground)
+        });
+
+        if agrees && !self.position_confirmed {
+            self.position_confirmed = true;
+            self.unconfirmed_position = None;
+            self.set_position(global_position, time);
+        } else {
+            self.reject_position();
+            self.unconfirmed_position = Some((global_position, time));
+        }
+    }
+
+    /// Store a global position that still has to be confirmed
+    ///
+    /// The stored CPR frames are dropped so the confirming decode comes from an independent pair.
+    ///
+    /// `position` - the new position
+    /// `time` - the time the position was received
+    fn set_unconfirmed_position(&mut self, position: GeographicPosition, time: DateTime<Local>) {
+        self.position_confirmed = false;
+        self.unconfirmed_position = None;
+        self.last_odd_cpr = None;
+        self.last_even_cpr = None;
+        self.set_position(position, time);
+    }
+
+    /// Store an accepted position
+    ///
+    /// `position` - the new position
+    /// `time` - the time the position was received
+    fn set_position(&mut self, position: GeographicPosition, time: DateTime<Local>) {
+        self.record_track_point(position.clone(), time);
+        self.geo_position = Some(position);
+        self.position_time = Some(time);
+        self.position_from_receiver = false;
+    }
+
+    /// Add an accepted position to the track history thinning and pruning the older points
+    ///
+    /// `position` - the new position
+    /// `time` - the time the position was received
+    fn record_track_point(&mut self, position: GeographicPosition, time: DateTime<Local>) {
+        let point = TrackPoint {
+            time: time.timestamp_millis(),
+            position: position,
+            altitude: self.altitude,
+            ground_speed: self.ground_speed,
+            track: self.track,
+            on_ground: self.on_ground,
+        };
+
+        // The last point isn't needed if it and every point already dropped from the segment
+        // still lie on a straight line from the point before to the new one
+        let len = self.track_history.len();
+        if len >= 2 {
+            let start = &self.track_history[len - 2];
+            let straight = self.thinned_points.iter()
+                .chain(self.track_history.back())
+                .all(|middle| is_straight_segment(start, middle, &point));
+
+            if straight {
+                self.thinned_points.extend(self.track_history.pop_back());
+            } else {
+                self.thinned_points.clear();
+            }
+        }
+        self.track_history.push_back(point);
+
+        // Age is measured from the newest point so playback keeps the same history as live data
+        let retention = &self.config.track_retention;
+        let oldest = time.timestamp_millis() - retention.max_age_s * 1000;
+        while self.track_history.len() > retention.max_points
+            || self.track_history.front().is_some_and(|point| point.time < oldest) {
+            self.track_history.pop_front();
+        }
+    }
+
+    /// Check a new position is within range of the receiver and reachable from the previous fix
+    ///
+    /// `position` - the new position
+    /// `time` - the time of the new position
+    ///
+    /// returns true if the position is plausible
+    fn is_position_reasonable(&self, position: &GeographicPosition, time: DateTime<Local>) -> bool {
+        if !self.is_in_range(position) {
+            return false;
+        }
+
+        if let (Some(previous), Some(position_time)) = (self.get_recent_position(time), self.position_time) {
+            return is_reachable(&previous, position_time, position, time, self.on_ground);
+        }
+
+        true
+    }
+
+    /// Check a position is within the maximum range of the receiver
+    fn is_in_range(&self, position: &GeographicPosition) -> bool {
+        match (&self.config.position, self.config.max_range_nm) {
+            (Some(receiver), Some(max_range)) => receiver.distance_nm(position) <= max_range,
+            _ => true,
+        }
+    }
+
+    /// Record a rejected position dropping the stored CPR frames so they are not paired again
+    fn reject_position(&mut self) {
+        self.rejected_positions += 1;
+        self.last_odd_cpr = None;
+        self.last_even_cpr = None;
+    }
+
+    /// Get the last known position if it is recent enough to be trusted
+    ///
+    /// A position only decoded against the receiver isn't trusted as it could be an alias.
+    ///
+    /// `time` - the time of the frame being decoded
+    fn get_recent_position(&self, time: DateTime<Local>) -> Option<GeographicPosition> {
+        if self.position_from_receiver {
+            return None;
+        }
+
+        let position_time = self.position_time?;
+
+        if (time - position_time).abs() > chrono::Duration::seconds(LOCAL_REFERENCE_TIMEOUT_S) {
+            return None;
+        }
+
+        self.geo_position.clone()
+    }
+
+    /// Get the last known position if it is confirmed and recent enough to decode frames locally against
+    ///
+    /// `time` - the time of the frame being decoded
+    fn get_reference_position(&self, time: DateTime<Local>) -> Option<GeographicPosition> {
+        if !self.position_confirmed {
+            return None;
+        }
+
+        self.get_recent_position(time)
+    }
+
+    /// Get the reference to decode a lone frame locally against
+    ///
+    /// The receiver is only used when the maximum range keeps every accepted aircraft within
+    /// half a zone of it otherwise a distant aircraft would alias to a position near the receiver.
+    /// A recent global fix that is still unconfirmed is kept until another pair confirms it.
+    ///
+    /// `reference_position` - the confirmed position of the aircraft if it is recent
+    /// `time` - the time of the frame being decoded
+    fn get_local_reference(&self, reference_position: Option<GeographicPosition>, time: DateTime<Local>) -> Option<GeographicPosition> {
+        if reference_position.is_some() {
+            return reference_position;
+        }
+
+        if self.get_recent_position(time).is_some() {
+            return None;
+        }
+
+        let limit = if self.on_ground { SURFACE_RECEIVER_REFERENCE_NM } else { AIRBORNE_RECEIVER_REFERENCE_NM };
+        match self.config.max_range_nm {
+            Some(max_range) if max_range <= limit => self.config.position.clone(),
+            _ => None,
+        }
+    }
+
+    /// Update the on ground state clearing stored CPR frames when it changes
+    /// as surface and airborne frames cannot be paired
+    fn set_on_ground(&mut self, on_ground: bool) {
+        if self.on_ground != on_ground {
+            self.last_odd_cpr = None;
+            self.last_even_cpr = None;
+        }
+
+        self.on_ground = on_ground;
+    }
+
+    /// Get the NIC supplements A and C from the last operational status message
+    /// defaulting to false if none has been received
+    fn get_nic_supplements(&self) -> (bool, bool) {
+        match &self.operational_status {
+            Some(status) => (status.nic_supplement_a, status.nic_supplement_c),
+            None => (false, false),
+        }
+    }
+
+    /// Store a CPR frame and pair it with the latest frame of the other format
+    ///
+    /// `format` - the format of the new frame
+    /// `cpr_position` - the raw CPR latitude and longitude of the new frame
+    /// `time` - the time the new frame was received
+    ///
+    /// returns the even position, odd position and the format of the older frame if they can be paired
+    fn pair_cpr_frame(&mut self, format: CprFormat, cpr_position: (u32, u32), time: DateTime<Local>) -> Option<CprPair> {
+        let window = chrono::Duration::seconds(if self.on_ground { SURFACE_CPR_WINDOW_S } else { AIRBORNE_CPR_WINDOW_S });
+
+        match format {
+            CprFormat::Even => {
+                self.last_even_cpr = Some(cpr_position);
+                self.last_even_processed = time;
+
+                let odd_position = self.last_odd_cpr?;
+                if (time - self.last_odd_processed).abs() > window {
+                    return None;
+                }
+
+                Some((cpr_position, odd_position, CprFormat::Odd))
+            },
+            CprFormat::Odd => {
+                self.last_odd_cpr = Some(cpr_position);
+                self.last_odd_processed = time;
+
+                let even_position = self.last_even_cpr?;
+                if (time - self.last_even_processed).abs() > window {
+                    return None;
+                }
+
+                Some((even_position, cpr_position, CprFormat::Even))
+            },
+        }
+    }
+
     pub fn get_icao(&self) -> u32 {
         self.icao
     }
 
+    /// Returns the type of address identifying the aircraft
+    pub fn get_address_type(&self) -> AddressType {
+        AddressType::from_address(self.icao)
+    }
+
+    /// Returns the address formatted as hex with a leading ~ for non ICAO addresses
+    pub fn get_address_string(&self) -> String {
+        format_address(self.icao)
+    }
+
+    /// Returns the source of the last extended squitter
+    pub fn get_source(&self) -> DataSource {
+        self.source
+    }
+
+    /// Returns the ADS-B callsign falling back to the Comm-B identification
     pub fn get_callsign(&self) -> String {
-        let callsign = match self.callsign.clone() {
-            Some(callsign) => callsign,
-            None => String::from(""),
-        };
+        self.callsign.clone().or(self.enhanced_surveillance.callsign.clone()).unwrap_or_default()
+    }
+
+    /// Returns the emitter category from the last identification message
+    pub fn get_category(&self) -> Option<EmitterCategory> {
+        self.category
+    }
+
+    /// Returns true if ADS-B has been received from the aircraft
+    pub fn is_adsb(&self) -> bool {
+        self.adsb
+    }
 
-        callsign
+    /// Returns the transponder capability from the last all call reply
+    pub fn get_capability(&self) -> Option<u8> {
+        self.capability
     }
 
-    pub fn get_altitude_ft(&self) -> i32 {
+    pub fn get_altitude_ft(&self) -> Option<i32> {
         self.altitude
     }
 
+    /// Returns the GNSS altitude in feet
+    pub fn get_geometric_altitude_ft(&self) -> Option<i32> {
+        self.geometric_altitude
+    }
+
     /// Return the time since the last transmission in seconds
-    pub fn get_age(&self) -> i64 {
-        (chrono::Local::now() - self.last_contact).num_seconds()
+    ///
+    /// `now` - the current time on the sample clock
+    pub fn get_age(&self, now: DateTime<Local>) -> i64 {
+        (now - self.last_contact).num_seconds()
+    }
+
+    /// Returns the time of the last transmission
+    pub fn get_last_contact(&self) -> DateTime<Local> {
+        self.last_contact
     }
 
     pub fn get_geo_position(&self) -> Option<GeographicPosition> {
         self.geo_position.clone()
     }
 
+    /// Returns the ground speed in knots
+    pub fn get_ground_speed(&self) -> Option<f64> {
+        self.ground_speed
+    }
+
+    /// Returns the track angle in degrees
+    pub fn get_track(&self) -> Option<f64> {
+        self.track
+    }
+
+    /// Returns the vertical rate in feet per minute
+    pub fn get_vertical_rate(&self) -> Option<i32> {
+        self.vertical_rate
+    }
+
+    /// Returns true if the aircraft last reported a surface position
+    pub fn is_on_ground(&self) -> bool {
+        self.on_ground
+    }
+
+    /// Returns the Mode A squawk as four octal digits
+    pub fn get_squawk(&self) -> Option<String> {
+        self.squawk.map(|squawk| format!("{:04o}", squawk))
+    }
+
+    /// Returns the last broadcast emergency state
+    pub fn get_emergency(&self) -> Option<EmergencyState> {
+        self.emergency
+    }
+
+    /// Returns true if the aircraft is declaring an emergency
+    pub fn is_emergency(&self) -> bool {
+        matches!(self.emergency, Some(state) if state != EmergencyState::NoEmergency)
+            || matches!(self.squawk, Some(0o7500 | 0o7600 | 0o7700))
+    }
+
+    /// Returns the autopilot selected altitude in feet
+    pub fn get_selected_altitude_ft(&self) -> Option<i32> {
+        self.selected_altitude
+    }
+
+    /// Returns the autopilot selected heading in degrees
+    pub fn get_selected_heading(&self) -> Option<f64> {
+        self.selected_heading
+    }
+
+    /// Returns the barometric pressure setting in millibars
+    pub fn get_baro_setting(&self) -> Option<f64> {
+        self.baro_setting
+    }
+
+    /// Returns the engaged autopilot modes
+    pub fn get_autopilot_modes(&self) -> Option<AutopilotModes> {
+        self.autopilot_modes
+    }
+
+    /// Returns the number of positions rejected as implausible
+    pub fn get_rejected_positions(&self) -> u32 {
+        self.rejected_positions
+    }
+
+    /// Returns the average signal power of recent frames in dBFS
+    pub fn get_signal_power(&self) -> Option<f64> {
+        if self.signal_powers.is_empty() {
+            return None;
+        }
+
+        // Average the linear power so strong frames are not under weighted
+        let power = self.signal_powers.iter()
+            .map(|power| 10f64.powf(power / 10.0))
+            .sum::<f64>() / self.signal_powers.len() as f64;
+
+        Some(10.0 * power.log10())
+    }
+
+    /// Returns the navigation integrity category of the last position
+    pub fn get_nic(&self) -> Option<u8> {
+        self.nic
+    }
+
+    /// Returns the containment radius of the last position in metres
+    pub fn get_containment_radius(&self) -> Option<f64> {
+        self.containment_radius
+    }
+
+    /// Get the integrity and accuracy of the reported position
+    pub fn get_integrity(&self) -> PositionIntegrity {
+        let status = self.operational_status.as_ref();
+
+        PositionIntegrity {
+            adsb_version: status.map(|status| status.version),
+            nic: self.get_nic(),
+            containment_radius: self.get_containment_radius(),
+            nac_p: status.map(|status| status.nac_p),
+            sil: status.map(|status| status.sil),
+            gva: status.and_then(|status| status.gva),
+            heading_reference: status.map(|status| status.heading_reference),
+            tcas_operational: status.is_some_and(|status| status.is_tcas_operational()),
+        }
+    }
+
+    /// Returns the state reported in Comm-B replies
+    pub fn get_enhanced_surveillance(&self) -> EnhancedSurveillance {
+        self.enhanced_surveillance.clone()
+    }
+
+    /// Get the recent positions of the aircraft for drawing its trail
+    /// Returns an AircraftTrack struct
+    pub fn get_track_history(&self) -> AircraftTrack {
+        AircraftTrack {
+            icao: self.icao,
+            points: self.track_history.iter().cloned().collect(),
+        }
+    }
+
     /// Get a summary of the aircraft information
     /// Returns an AircraftSummary struct
     pub fn get_summary(&self) -> AircraftSummary {
         AircraftSummary {
             icao: self.icao,
+            address_type: self.get_address_type(),
+            source: self.get_source(),
             callsign: self.get_callsign(),
+            category: self.get_category(),
+            adsb: self.is_adsb(),
+            capability: self.get_capability(),
             altitude: self.get_altitude_ft(),
+            geometric_altitude: self.get_geometric_altitude_ft(),
             geo_position: self.get_geo_position(),
+            ground_speed: self.get_ground_speed(),
+            track: self.get_track(),
+            vertical_rate: self.get_vertical_rate(),
+            on_ground: self.is_on_ground(),
+            squawk: self.get_squawk(),
+            emergency: self.get_emergency(),
+            selected_altitude: self.get_selected_altitude_ft(),
+            selected_heading: self.get_selected_heading(),
+            baro_setting: self.get_baro_setting(),
+            autopilot_modes: self.get_autopilot_modes(),
+            rejected_positions: self.get_rejected_positions(),
+            signal_power: self.get_signal_power(),
+            integrity: self.get_integrity(),
+            enhanced_surveillance: self.get_enhanced_surveillance(),
             last_contact: self.last_contact.timestamp(),
         }
     }
 }
 
-/// Handle recieving aircraft packets and updating the aircraft information.
-/// Adding an aircraft to the hashmap if it does not exist or updating it if it does.
-/// 
-/// 'packet' - the ADS-B packet to handle
-/// 'aircrafts' - a hashmap of aircrafts to update
-/// returns the aircraft that was updated or added
-pub fn handle_aircraft_update(packet: adsb::AdsbPacket, aircrafts: &mut std::collections::HashMap<u32, Aircraft>) -> Option<Aircraft> {
-    let icao = packet.get_icao();
-    let aircraft = aircrafts.entry(icao).or_insert(Aircraft::new(icao));
-    
-    aircraft.handle_packet(packet);
-    
-    Some(aircraft.clone())
-}
-
 mod tests {
     #[allow(unused_imports)]
     use std::str::FromStr;
@@ -173,13 +1031,15 @@ mod tests {
     use crate::adsb::msgs::AdsbMsgType;
     #[allow(unused_imports)]
     use crate::adsb::AdsbPacket;
+    #[allow(unused_imports)]
+    use crate::adsb::demod::SampleClock;
 
     #[test]
     fn test_aircraft_new() {
         let aircraft = Aircraft::new(0x123456);
         assert_eq!(aircraft.get_icao(), 0x123456);
         assert_eq!(aircraft.get_callsign(), "");
-        assert_eq!(aircraft.get_altitude_ft(), 0);
+        assert_eq!(aircraft.get_altitude_ft(), None);
     }
 
     #[test]
@@ -188,6 +1048,7 @@ mod tests {
         let packet = AdsbPacket::_new_from_string(String::from_str("8d7c6b3020293532d70820fc8090").unwrap());
         aircraft.handle_packet(packet);
         assert_eq!(aircraft.get_callsign(), "JST250__");
+        assert_eq!(aircraft.get_category(), Some(EmitterCategory::NoInformation));
     }
 
     #[test]
@@ -195,7 +1056,17 @@ mod tests {
         let mut aircraft = Aircraft::new(0x7C6B30);
         let packet = AdsbPacket::_new_from_string(String::from_str("8d7c6b30581304f388bb4455896f").unwrap());
         aircraft.handle_packet(packet);
-        assert_eq!(aircraft.get_altitude_ft(), 2600);
+        assert_eq!(aircraft.get_altitude_ft(), Some(2600));
+    }
+
+    #[test]
+    fn test_aircraft_handle_packet_velocity() {
+        let mut aircraft = Aircraft::new(0x485020);
+        let packet = AdsbPacket::_new_from_string(String::from_str("8D485020994409940838175B284F").unwrap());
+        aircraft.handle_packet(packet);
+        assert!((aircraft.get_ground_speed().unwrap() - 159.20).abs() < 0.01);
+        assert!((aircraft.get_track().unwrap() - 182.88).abs() < 0.01);
+        assert_eq!(aircraft.get_vertical_rate(), Some(-832));
     }
 
     #[test]
@@ -206,9 +1077,9 @@ mod tests {
 
         aircraft.handle_packet(first);
         aircraft.handle_packet(second);
-        assert_eq!(aircraft.get_altitude_ft(), 38000);
+        assert_eq!(aircraft.get_altitude_ft(), Some(38000));
         assert!((aircraft.geo_position.clone().unwrap().latitude - 52.25720).abs() < 0.0001);
-        assert!((aircraft.geo_position.unwrap().longitude - 3.829498291015625).abs() < 0.0001);
+        assert!((aircraft.geo_position.unwrap().longitude - 3.91937255859375).abs() < 0.0001);
     }
 
     #[test]
@@ -256,8 +1127,474 @@ mod tests {
         aircraft.handle_packet(first);
         aircraft.handle_packet(second);
 
-        assert_eq!(aircraft.get_altitude_ft(), 1450);
+        assert_eq!(aircraft.get_altitude_ft(), Some(1450));
         assert!((aircraft.geo_position.clone().unwrap().latitude - -41.28964698920816).abs() < 0.0001);
         assert!((aircraft.geo_position.unwrap().longitude - 174.80927207253197).abs() < 0.0001);
     }
+
+    #[test]
+    fn test_aircraft_handle_packet_sample_time() {
+        // Frames stamped from the sample stream are paired by when they were received
+        // not by when they were decoded
+        let clock = SampleClock::new(chrono::Local::now(), 2_400_000.0);
+        let stamp = |packet: &str, sample: u64| {
+            let mut packet = AdsbPacket::_new_from_string(String::from_str(packet).unwrap());
+            packet.timestamp = Some(clock.get_ticks(sample));
+            packet.time_processed = clock.get_time(sample);
+            packet
+        };
+
+        let mut aircraft = Aircraft::new(0x7C6B30);
+        aircraft.handle_packet(stamp("8d7c6b30580d107903b3cabf62ab", 0));
+        aircraft.handle_packet(stamp("8d7c6b30580d24eeaebb2dfea5bb", 2_400_000 * 15));
+        assert!(aircraft.geo_position.is_none());
+
+        let mut aircraft = Aircraft::new(0x7C6B30);
+        aircraft.handle_packet(stamp("8d7c6b30580d107903b3cabf62ab", 0));
+        aircraft.handle_packet(stamp("8d7c6b30580d24eeaebb2dfea5bb", 2_400_000 / 2));
+        assert!((aircraft.geo_position.unwrap().latitude - -41.28964698920816).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_aircraft_track_history() {
+        let mut aircraft = Aircraft::new(0x7C6B30);
+        let first = AdsbPacket::_new_from_string(String::from_str("8d7c6b30580d107903b3cabf62ab").unwrap());
+        let second = AdsbPacket::_new_from_string(String::from_str("8d7c6b30580d24eeaebb2dfea5bb").unwrap());
+        let time = second.time_processed;
+
+        aircraft.handle_packet(first);
+        aircraft.handle_packet(second);
+
+        let track = aircraft.get_track_history();
+        assert_eq!(track.icao, 0x7C6B30);
+        assert_eq!(track.points.len(), 1);
+        assert_eq!(track.points[0].time, time.timestamp_millis());
+        assert_eq!(track.points[0].altitude, aircraft.get_altitude_ft());
+        assert!((track.points[0].position.latitude - -41.28964698920816).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_aircraft_track_history_thinning() {
+        let mut aircraft = Aircraft::new(0x7C6B30);
+        let start = chrono::Local::now();
+
+        // Climb east, turn north and level off, then descend for a long straight leg
+        let mut flight = Vec::new();
+        for i in 0..300 {
+            let (latitude, longitude, altitude) = match i {
+                0..=29 => (-41.0, 174.0 + i as f64 * 0.002, 10000 + i * 20),
+                30..=59 => (-41.0 + (i - 29) as f64 * 0.002, 174.058, 10580 + (i - 29).min(10) * 20),
+                _ => (-40.94 + (i - 59) as f64 * 0.002, 174.058, 10780 - (i - 59) * 25),
+            };
+            let point = TrackPoint {
+                time: (start + chrono::Duration::seconds(i as i64)).timestamp_millis(),
+                position: GeographicPosition { latitude, longitude },
+                altitude: Some(altitude),
+                ground_speed: None,
+                track: None,
+                on_ground: false,
+            };
+
+            aircraft.altitude = point.altitude;
+            aircraft.record_track_point(point.position.clone(), start + chrono::Duration::seconds(i as i64));
+            flight.push(point);
+        }
+
+        let points = aircraft.get_track_history().points;
+        assert!(points.len() < 20, "kept {} points", points.len());
+        assert_eq!(points.first().unwrap().time, flight.first().unwrap().time);
+        assert_eq!(points.last().unwrap().time, flight.last().unwrap().time);
+
+        // The corner and every dropped point are still on the trail
+        assert!(points.iter().any(|point| point.position.latitude == -41.0 && point.position.longitude == 174.058));
+        for point in flight.iter() {
+            let segment = points.windows(2)
+                .find(|pair| pair[0].time <= point.time && point.time <= pair[1].time)
+                .unwrap();
+            assert!(segment[1].time - segment[0].time <= TRACK_THINNING_MAX_GAP_MS);
+            assert!(point.position.cross_track_nm(&segment[0].position, &segment[1].position) <= TRACK_THINNING_OFFSET_NM);
+
+            let fraction = (point.time - segment[0].time) as f64 / (segment[1].time - segment[0].time).max(1) as f64;
+            let (first, last) = (segment[0].altitude.unwrap() as f64, segment[1].altitude.unwrap() as f64);
+            assert!((point.altitude.unwrap() as f64 - (first + (last - first) * fraction)).abs() <= TRACK_THINNING_ALTITUDE_FT as f64);
+        }
+    }
+
+    #[test]
+    fn test_aircraft_track_history_retention() {
+        let config = ReceiverConfig { track_retention: TrackRetention { max_age_s: 60, max_points: 5 }, ..Default::default() };
+        let mut aircraft = Aircraft::with_config(0x7C6B30, config);
+        let start = chrono::Local::now();
+
+        // Zig zag so no points are thinned
+        for i in 0..8 {
+            let latitude = if i % 2 == 0 { -41.0 } else { -41.01 };
+            aircraft.record_track_point(GeographicPosition { latitude, longitude: 174.0 + i as f64 * 0.01 }, start + chrono::Duration::seconds(i));
+        }
+        let points = aircraft.get_track_history().points;
+        assert_eq!(points.len(), 5);
+        assert_eq!(points[0].time, (start + chrono::Duration::seconds(3)).timestamp_millis());
+
+        // Old points are dropped relative to the newest position
+        aircraft.record_track_point(GeographicPosition { latitude: -41.0, longitude: 174.2 }, start + chrono::Duration::seconds(66));
+        let points = aircraft.get_track_history().points;
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0].time, (start + chrono::Duration::seconds(6)).timestamp_millis());
+    }
+
+    #[test]
+    fn test_aircraft_handle_packet_surface() {
+        let config = ReceiverConfig { position: Some(GeographicPosition { latitude: 51.990, longitude: 4.375 }), max_range_nm: None, ..Default::default() };
+        let mut aircraft = Aircraft::with_config(0x484175, config);
+        let first = AdsbPacket::_new_from_string(String::from_str("8C4841753AAB238733C8CD4020B1").unwrap());
+        let mut second = AdsbPacket::_new_from_string(String::from_str("8C4841753A8A35323FAEBDAC702D").unwrap());
+        second.time_processed = first.time_processed + chrono::Duration::seconds(10);
+
+        aircraft.handle_packet(first);
+        aircraft.handle_packet(second);
+
+        assert!(aircraft.is_on_ground());
+        assert!((aircraft.get_ground_speed().unwrap() - 16.0).abs() < 0.01);
+        assert!((aircraft.geo_position.clone().unwrap().latitude - 52.32061).abs() < 0.0001);
+        assert!((aircraft.geo_position.unwrap().longitude - 4.73473).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_aircraft_handle_packet_local_after_global() {
+        let mut aircraft = Aircraft::new(0x7C6B30);
+        let first = AdsbPacket::_new_from_string(String::from_str("8d7c6b30580d107903b3cabf62ab").unwrap());
+        let second = AdsbPacket::_new_from_string(String::from_str("8d7c6b30580d24eeaebb2dfea5bb").unwrap());
+
+        aircraft.handle_packet(first.clone());
+        aircraft.handle_packet(second.clone());
+
+        // A second independent pair confirms the fix
+        let mut confirm_first = first.clone();
+        let mut confirm_second = second;
+        confirm_first.time_processed += chrono::Duration::seconds(1);
+        confirm_second.time_processed += chrono::Duration::seconds(1);
+        aircraft.handle_packet(confirm_first);
+        aircraft.handle_packet(confirm_second);
+        assert!(aircraft.position_confirmed);
+
+        // A lone even frame long after the pair is decoded against the confirmed fix
+        let mut late = first;
+        late.time_processed += chrono::Duration::seconds(30);
+        aircraft.handle_packet(late);
+
+        assert!((aircraft.geo_position.clone().unwrap().latitude - -41.29097).abs() < 0.0001);
+        assert!((aircraft.geo_position.unwrap().longitude - 174.80920).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_aircraft_handle_packet_local_receiver() {
+        let config = ReceiverConfig { position: Some(GeographicPosition { latitude: -41.3, longitude: 174.8 }), max_range_nm: Some(150.0), ..Default::default() };
+        let mut aircraft = Aircraft::with_config(0x7C6B30, config);
+        let packet = AdsbPacket::_new_from_string(String::from_str("8d7c6b30580d24eeaebb2dfea5bb").unwrap());
+
+        aircraft.handle_packet(packet);
+
+        assert!((aircraft.geo_position.clone().unwrap().latitude - -41.28965).abs() < 0.0001);
+        assert!((aircraft.geo_position.unwrap().longitude - 174.80927).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_aircraft_local_receiver_not_a_baseline() {
+        let config = ReceiverConfig { position: Some(GeographicPosition { latitude: -41.3, longitude: 174.8 }), max_range_nm: Some(150.0), ..Default::default() };
+        let mut aircraft = Aircraft::with_config(0x7C6B30, config);
+        let (even, odd) = get_wellington_pair(Local::now());
+
+        aircraft.handle_packet(odd.clone());
+        assert!(aircraft.get_geo_position().is_some());
+
+        // The receiver decode can't confirm the global pair so it waits for a second one
+        let mut even_after = even.clone();
+        even_after.time_processed = odd.time_processed + chrono::Duration::milliseconds(500);
+        aircraft.handle_packet(even_after);
+        assert!(!aircraft.position_confirmed);
+
+        let (even, odd) = get_wellington_pair(Local::now() + chrono::Duration::seconds(2));
+        aircraft.handle_packet(even);
+        aircraft.handle_packet(odd);
+        assert!(aircraft.position_confirmed);
+        assert_eq!(aircraft.get_rejected_positions(), 0);
+    }
+
+    #[test]
+    fn test_aircraft_no_local_receiver_beyond_half_zone() {
+        // About 250 NM south of the aircraft so a lone frame would alias to a zone further south
+        let config = ReceiverConfig { position: Some(GeographicPosition { latitude: -45.5, longitude: 174.8 }), max_range_nm: Some(300.0), ..Default::default() };
+        let mut aircraft = Aircraft::with_config(0x7C6B30, config);
+        let (even, odd) = get_wellington_pair(Local::now());
+
+        aircraft.handle_packet(even);
+        assert!(aircraft.get_geo_position().is_none());
+        assert!(aircraft.get_track_history().points.is_empty());
+
+        // The global pair resolves the real position
+        aircraft.handle_packet(odd);
+        let position = aircraft.get_geo_position().unwrap();
+        assert!((position.latitude - -41.28965).abs() < 0.0001);
+        assert!((position.longitude - 174.80927).abs() < 0.0001);
+        assert_eq!(aircraft.get_rejected_positions(), 0);
+    }
+
+    #[test]
+    fn test_aircraft_reject_out_of_range() {
+        let config = ReceiverConfig { position: Some(GeographicPosition { latitude: -41.3, longitude: 174.8 }), max_range_nm: Some(200.0), ..Default::default() };
+        let mut aircraft = Aircraft::with_config(0x40621D, config);
+        let first = AdsbPacket::_new_from_string(String::from_str("8D40621D58C386435CC412692AD6").unwrap());
+        let second = AdsbPacket::_new_from_string(String::from_str("8D40621D58C382D690C8AC2863A7").unwrap());
+
+        aircraft.handle_packet(first);
+        aircraft.handle_packet(second);
+
+        // The range is too large to decode the lone frame against the receiver and the global pair is out of range
+        assert!(aircraft.get_geo_position().is_none());
+        assert_eq!(aircraft.get_rejected_positions(), 1);
+    }
+
+    #[test]
+    fn test_aircraft_reject_global_disagreement() {
+        let mut aircraft = Aircraft::new(0x7C6B30);
+        let first = AdsbPacket::_new_from_string(String::from_str("8d7c6b30580d107903b3cabf62ab").unwrap());
+        let second = AdsbPacket::_new_from_string(String::from_str("8d7c6b30580d24eeaebb2dfea5bb").unwrap());
+        aircraft.handle_packet(first);
+        aircraft.handle_packet(second);
+
+        // A pair from an aircraft over the Netherlands cannot follow a fix over Wellington
+        let mut third = AdsbPacket::_new_from_string(String::from_str("8D40621D58C386435CC412692AD6").unwrap());
+        let mut fourth = AdsbPacket::_new_from_string(String::from_str("8D40621D58C382D690C8AC2863A7").unwrap());
+        third.icao = 0x7C6B30;
+        fourth.icao = 0x7C6B30;
+        aircraft.handle_packet(third);
+        aircraft.handle_packet(fourth);
+
+        // Only the global decode of the second pair is rejected and the good fix is kept
+        let position = aircraft.get_geo_position().unwrap();
+        assert!((position.latitude - -41.28965).abs() < 0.0001);
+        assert!((position.longitude - 174.80927).abs() < 0.0001);
+        assert_eq!(aircraft.get_rejected_positions(), 1);
+    }
+
+    /// Get a pair of frames from an aircraft over Wellington received at the given time
+    #[cfg(test)]
+    fn get_wellington_pair(time: DateTime<Local>) -> (AdsbPacket, AdsbPacket) {
+        let mut even = AdsbPacket::_new_from_string(String::from_str("8d7c6b30580d107903b3cabf62ab").unwrap());
+        let mut odd = AdsbPacket::_new_from_string(String::from_str("8d7c6b30580d24eeaebb2dfea5bb").unwrap());
+        even.time_processed = time;
+        odd.time_processed = time + chrono::Duration::milliseconds(500);
+
+        (even, odd)
+    }
+
+    #[test]
+    fn test_aircraft_wrong_first_fix_recovers() {
+        let mut aircraft = Aircraft::new(0x7C6B30);
+        let time = Local::now();
+
+        // A bogus first global fix a zone to the north
+        aircraft.geo_position = Some(GeographicPosition { latitude: -35.29, longitude: 174.809 });
+        aircraft.position_time = Some(time);
+
+        // The bogus fix is not a local reference so lone frames are not resolved against it
+        let (even, odd) = get_wellington_pair(time + chrono::Duration::seconds(1));
+        aircraft.handle_packet(even);
+        assert!((aircraft.get_geo_position().unwrap().latitude - -35.29).abs() < 0.0001);
+
+        // The first correct pair is rejected and two agreeing pairs replace the fix
+        aircraft.handle_packet(odd);
+        assert_eq!(aircraft.get_rejected_positions(), 1);
+
+        let (even, odd) = get_wellington_pair(time + chrono::Duration::seconds(2));
+        aircraft.handle_packet(even);
+        aircraft.handle_packet(odd);
+
+        let position = aircraft.get_geo_position().unwrap();
+        assert!((position.latitude - -41.28965).abs() < 0.0001);
+        assert!((position.longitude - 174.80927).abs() < 0.0001);
+        assert!(aircraft.position_confirmed);
+        assert_eq!(aircraft.get_rejected_positions(), 1);
+    }
+
+    #[test]
+    fn test_aircraft_wrong_confirmed_fix_dropped() {
+        let mut aircraft = Aircraft::new(0x7C6B30);
+        let time = Local::now();
+
+        // A bogus fix a zone to the north that was confirmed by repeating the same error
+        let bogus = GeographicPosition { latitude: -35.3, longitude: 174.8 };
+        aircraft.geo_position = calculate_local_position((15489, 111562), CprFormat::Even, &bogus, false);
+        aircraft.position_time = Some(time);
+        aircraft.position_confirmed = true;
+
+        // The lone even frame resolves next to the bogus fix
+        let (even, odd) = get_wellington_pair(time + chrono::Duration::seconds(1));
+        aircraft.handle_packet(even);
+        assert!((aircraft.get_geo_position().unwrap().latitude - -35.3).abs() < 0.1);
+        assert_eq!(aircraft.get_rejected_positions(), 0);
+
+        // The global pair disagrees with the local decode so the reference is dropped
+        aircraft.handle_packet(odd);
+        assert_eq!(aircraft.get_rejected_positions(), 1);
+        assert!(!aircraft.position_confirmed);
+        assert!((aircraft.get_geo_position().unwrap().latitude - -41.28965).abs() < 0.0001);
+
+        // An independent pair confirms the correct position
+        let (even, odd) = get_wellington_pair(time + chrono::Duration::seconds(2));
+        aircraft.handle_packet(even);
+        aircraft.handle_packet(odd);
+
+        let position = aircraft.get_geo_position().unwrap();
+        assert!((position.latitude - -41.28965).abs() < 0.0001);
+        assert!(aircraft.position_confirmed);
+        assert_eq!(aircraft.get_rejected_positions(), 1);
+    }
+
+    #[test]
+    fn test_aircraft_reject_impossible_speed() {
+        let mut aircraft = Aircraft::new(0x7C6B30);
+        let time = Local::now();
+        aircraft.geo_position = Some(GeographicPosition { latitude: -41.3, longitude: 174.8 });
+        aircraft.position_time = Some(time);
+
+        let near = GeographicPosition { latitude: -41.31, longitude: 174.8 };
+        let far = GeographicPosition { latitude: -41.6, longitude: 174.8 };
+
+        assert!(aircraft.is_position_reasonable(&near, time + chrono::Duration::seconds(2)));
+        assert!(!aircraft.is_position_reasonable(&far, time + chrono::Duration::seconds(2)));
+    }
+
+    #[test]
+    fn test_aircraft_handle_packet_gnss_alt() {
+        let mut aircraft = Aircraft::new(0x7C6B30);
+        let baro = AdsbPacket::_new_from_string(String::from_str("8d7c6b30581304f388bb4455896f").unwrap());
+        let gnss = AdsbPacket::_new_from_string(String::from_str("8D7C6B30A03E82D690C8AC000000").unwrap());
+
+        aircraft.handle_packet(baro);
+        aircraft.handle_packet(gnss);
+
+        assert_eq!(aircraft.get_altitude_ft(), Some(2600));
+        assert_eq!(aircraft.get_geometric_altitude_ft(), Some(3281));
+    }
+
+    #[test]
+    fn test_aircraft_handle_packet_integrity() {
+        let mut aircraft = Aircraft::new(0x40621D);
+        let position = AdsbPacket::_new_from_string(String::from_str("8D40621D58C382D690C8AC2863A7").unwrap());
+        let status = AdsbPacket::_new_from_string(String::from_str("8D40621DF8300000005AB8000000").unwrap());
+
+        aircraft.handle_packet(position.clone());
+        assert_eq!(aircraft.get_nic(), Some(8));
+        assert_eq!(aircraft.get_containment_radius(), Some(185.2));
+
+        aircraft.handle_packet(status);
+        aircraft.handle_packet(position);
+        let integrity = aircraft.get_integrity();
+
+        assert_eq!(integrity.adsb_version, Some(2));
+        assert_eq!(integrity.nac_p, Some(10));
+        assert_eq!(integrity.sil, Some(3));
+        assert_eq!(integrity.gva, Some(2));
+        assert!(integrity.tcas_operational);
+        // NIC supplement B is not set so supplement A alone does not improve the NIC
+        assert_eq!(integrity.nic, Some(8));
+    }
+
+    #[test]
+    fn test_aircraft_handle_packet_emergency() {
+        let mut aircraft = Aircraft::new(0x40621D);
+        let normal = AdsbPacket::_new_from_string(String::from_str("8D40621DE1080800000000000000").unwrap());
+        let emergency = AdsbPacket::_new_from_string(String::from_str("8D40621DE12AAA00000000000000").unwrap());
+
+        aircraft.handle_packet(normal);
+        assert_eq!(aircraft.get_squawk(), Some(String::from("1200")));
+        assert!(!aircraft.is_emergency());
+
+        aircraft.handle_packet(emergency);
+        assert_eq!(aircraft.get_squawk(), Some(String::from("7700")));
+        assert_eq!(aircraft.get_emergency(), Some(EmergencyState::General));
+        assert!(aircraft.is_emergency());
+    }
+
+    #[test]
+    fn test_aircraft_handle_packet_target_state() {
+        let mut aircraft = Aircraft::new(0xA05629);
+        let packet = AdsbPacket::_new_from_string(String::from_str("8DA05629EA21485CBF3F8CADAEEB").unwrap());
+
+        aircraft.handle_packet(packet);
+
+        assert_eq!(aircraft.get_selected_altitude_ft(), Some(16992));
+        assert!((aircraft.get_selected_heading().unwrap() - 66.797).abs() < 0.01);
+        assert!((aircraft.get_baro_setting().unwrap() - 1012.8).abs() < 0.01);
+        assert!(aircraft.get_autopilot_modes().unwrap().lnav);
+    }
+
+    #[test]
+    fn test_aircraft_handle_packet_all_call() {
+        let mut aircraft = Aircraft::new(0x7C6B30);
+        let reply = AdsbPacket::_new_from_string(String::from_str("5D7C6B30000000").unwrap());
+
+        aircraft.handle_packet(reply);
+        assert_eq!(aircraft.get_capability(), Some(5));
+        assert!(!aircraft.is_adsb());
+
+        let packet = AdsbPacket::_new_from_string(String::from_str("8d7c6b3020293532d70820fc8090").unwrap());
+        aircraft.handle_packet(packet);
+        assert!(aircraft.is_adsb());
+    }
+
+    #[test]
+    fn test_aircraft_handle_packet_surveillance() {
+        let mut aircraft = Aircraft::new(0x7C6B30);
+        // DF4 at 38000 ft and DF5 squawking 7700 with the address overlaid on the parity
+        let altitude = AdsbPacket::_new_from_string(String::from_str("200018386DE86B").unwrap());
+        let identity = AdsbPacket::_new_from_string(String::from_str("28000AAA36CFF9").unwrap());
+
+        aircraft.handle_packet(altitude);
+        aircraft.handle_packet(identity);
+
+        assert_eq!(aircraft.get_altitude_ft(), Some(38000));
+        assert_eq!(aircraft.get_squawk(), Some(String::from("7700")));
+        assert!(!aircraft.is_adsb());
+    }
+
+    #[test]
+    fn test_aircraft_handle_packet_comm_b() {
+        let mut aircraft = Aircraft::new(0x7C6B30);
+        // DF20 carrying BDS 5,0 and DF21 carrying BDS 2,0 with the address overlaid on the parity
+        let track = AdsbPacket::_new_from_string(String::from_str("A000139381951536E024D48CD057").unwrap());
+        let identity = AdsbPacket::_new_from_string(String::from_str("A8000AAA202CC371C32CE04612F0").unwrap());
+
+        aircraft.handle_packet(track);
+        aircraft.handle_packet(identity);
+
+        let enhanced_surveillance = aircraft.get_enhanced_surveillance();
+        assert_eq!(enhanced_surveillance.ground_speed, Some(438.0));
+        assert_eq!(enhanced_surveillance.true_airspeed, Some(424.0));
+        assert_eq!(aircraft.get_ground_speed(), None);
+        assert_eq!(aircraft.get_callsign(), "KLM1023_");
+        assert_eq!(aircraft.get_squawk(), Some(String::from("7700")));
+    }
+
+    #[test]
+    fn test_aircraft_signal_power() {
+        let mut aircraft = Aircraft::new(0x7C6B30);
+        let mut packet = AdsbPacket::_new_from_string(String::from_str("8d7c6b3020293532d70820fc8090").unwrap());
+
+        aircraft.handle_packet(packet.clone());
+        assert_eq!(aircraft.get_signal_power(), None);
+
+        packet.signal_power = Some(-10.0);
+        aircraft.handle_packet(packet.clone());
+        packet.signal_power = Some(-20.0);
+        aircraft.handle_packet(packet.clone());
+        assert!((aircraft.get_signal_power().unwrap() - -12.596).abs() < 0.001);
+
+        // Only the most recent frames are averaged
+        for _ in 0..SIGNAL_HISTORY_LEN {
+            aircraft.handle_packet(packet.clone());
+        }
+        assert!((aircraft.get_signal_power().unwrap() - -20.0).abs() < 0.001);
+    }
 }
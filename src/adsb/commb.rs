@@ -0,0 +1,394 @@
+/// Inference and decoding of Comm-B (DF20 and DF21) BDS registers
+///
+/// The register number is not transmitted so each candidate register is decoded
+/// and checked for consistency, the MB field is only used if exactly one matches.
+///
+/// Author: Jack Duignan (JackpDuignan@gmail.com)
+
+use crate::adsb::msgs::{get_bits, CHAR_CONVERT};
+
+/// BDS 4,0 selected vertical intention
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerticalIntention {
+    /// MCP/FCU selected altitude in feet
+    pub mcp_altitude: Option<i32>,
+    /// FMS selected altitude in feet
+    pub fms_altitude: Option<i32>,
+    /// Barometric pressure setting in millibars
+    pub baro_setting: Option<f64>,
+}
+
+/// BDS 5,0 track and turn report
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackTurn {
+    /// Roll angle in degrees, negative for left wing down
+    pub roll: Option<f64>,
+    /// True track angle in degrees
+    pub true_track: Option<f64>,
+    /// Ground speed in knots
+    pub ground_speed: Option<f64>,
+    /// Track angle rate in degrees per second
+    pub track_rate: Option<f64>,
+    /// True airspeed in knots
+    pub true_airspeed: Option<f64>,
+}
+
+/// BDS 6,0 heading and speed report
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeadingSpeed {
+    /// Magnetic heading in degrees
+    pub magnetic_heading: Option<f64>,
+    /// Indicated airspeed in knots
+    pub indicated_airspeed: Option<f64>,
+    pub mach: Option<f64>,
+    /// Barometric altitude rate in feet per minute
+    pub baro_vertical_rate: Option<i32>,
+    /// Inertial vertical rate in feet per minute
+    pub inertial_vertical_rate: Option<i32>,
+}
+
+/// BDS 4,4 routine meteorological report
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeteorologicalRoutine {
+    /// Wind speed in knots
+    pub wind_speed: Option<f64>,
+    /// Wind direction in degrees
+    pub wind_direction: Option<f64>,
+    /// Static air temperature in degrees celsius
+    pub temperature: f64,
+    /// Average static pressure in hectopascals
+    pub pressure: Option<f64>,
+    /// Turbulence level from 0 (nil) to 3 (severe)
+    pub turbulence: Option<u8>,
+    /// Relative humidity in percent
+    pub humidity: Option<f64>,
+}
+
+/// BDS 4,5 meteorological hazard report, hazard levels are 0 (nil) to 3 (severe)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeteorologicalHazard {
+    pub turbulence: Option<u8>,
+    pub wind_shear: Option<u8>,
+    pub microburst: Option<u8>,
+    pub icing: Option<u8>,
+    pub wake_vortex: Option<u8>,
+    /// Static air temperature in degrees celsius
+    pub temperature: Option<f64>,
+    /// Average static pressure in hectopascals
+    pub pressure: Option<f64>,
+    /// Radio height in feet
+    pub radio_height: Option<i32>,
+}
+
+/// A decoded Comm-B register
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommB {
+    /// BDS 2,0 aircraft identification
+    Identification(String),
+    /// BDS 4,0
+    VerticalIntention(VerticalIntention),
+    /// BDS 4,4
+    MeteorologicalRoutine(MeteorologicalRoutine),
+    /// BDS 4,5
+    MeteorologicalHazard(MeteorologicalHazard),
+    /// BDS 5,0
+    TrackTurn(TrackTurn),
+    /// BDS 6,0
+    HeadingSpeed(HeadingSpeed),
+}
+
+impl CommB {
+    /// Infer the register held in an MB field and decode it
+    ///
+    /// `mb` - the 56 bit MB field of a DF20 or DF21 reply
+    ///
+    /// returns the decoded register or None if no register or more than one register fits
+    pub fn infer(mb: &[u8; 7]) -> Option<Self> {
+        if mb.iter().all(|byte| *byte == 0) {
+            return None;
+        }
+
+        // Identification is the only register that carries its number
+        if mb[0] == 0x20 {
+            return decode_identification(mb).map(CommB::Identification);
+        }
+
+        let mut candidates = [
+            decode_vertical_intention(mb).map(CommB::VerticalIntention),
+            decode_meteorological_routine(mb).map(CommB::MeteorologicalRoutine),
+            decode_meteorological_hazard(mb).map(CommB::MeteorologicalHazard),
+            decode_track_turn(mb).map(CommB::TrackTurn),
+            decode_heading_speed(mb).map(CommB::HeadingSpeed),
+        ].into_iter().flatten();
+
+        match (candidates.next(), candidates.next()) {
+            (Some(register), None) => Some(register),
+            _ => None,
+        }
+    }
+
+    /// Returns the register number in the usual X,Y notation
+    pub fn get_bds(&self) -> &'static str {
+        match self {
+            CommB::Identification(_) => "2,0",
+            CommB::VerticalIntention(_) => "4,0",
+            CommB::MeteorologicalRoutine(_) => "4,4",
+            CommB::MeteorologicalHazard(_) => "4,5",
+            CommB::TrackTurn(_) => "5,0",
+            CommB::HeadingSpeed(_) => "6,0",
+        }
+    }
+}
+
+impl std::fmt::Display for CommB {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Comm-B BDS          : {}", self.get_bds())?;
+        match self {
+            CommB::Identification(callsign) => writeln!(f, "Callsign            : {}", callsign),
+            CommB::VerticalIntention(intention) => writeln!(f, "Vertical Intention  : {:?}", intention),
+            CommB::MeteorologicalRoutine(met) => writeln!(f, "Meteorological      : {:?}", met),
+            CommB::MeteorologicalHazard(hazard) => writeln!(f, "Hazard              : {:?}", hazard),
+            CommB::TrackTurn(track) => writeln!(f, "Track and Turn      : {:?}", track),
+            CommB::HeadingSpeed(heading) => writeln!(f, "Heading and Speed   : {:?}", heading),
+        }
+    }
+}
+
+/// Read a field that follows a status bit
+///
+/// `mb` - the MB field
+/// `status` - the status bit (1 indexed)
+/// `len` - the number of bits in the field following the status bit
+///
+/// returns None if the field is not valid but holds data, otherwise the field if it is valid
+fn get_status_field(mb: &[u8; 7], status: usize, len: usize) -> Option<Option<u32>> {
+    let value = get_bits(mb, status + 1, len);
+
+    match get_bits(mb, status, 1) {
+        1 => Some(Some(value)),
+        _ if value == 0 => Some(None),
+        _ => None,
+    }
+}
+
+/// Apply a sign bit to a magnitude field
+///
+/// `value` - the field including the leading sign bit
+/// `len` - the number of bits in the field including the sign
+fn to_signed(value: u32, len: usize) -> i32 {
+    if value >> (len - 1) == 1 {
+        value as i32 - (1 << len)
+    } else {
+        value as i32
+    }
+}
+
+/// Decode BDS 2,0 requiring every character to be valid
+fn decode_identification(mb: &[u8; 7]) -> Option<String> {
+    let callsign: String = (0..8)
+        .map(|i| CHAR_CONVERT[get_bits(mb, 9 + i * 6, 6) as usize])
+        .collect();
+
+    if callsign.contains('#') || callsign.chars().all(|ch| ch == '_') {
+        return None;
+    }
+
+    Some(callsign)
+}
+
+/// Decode BDS 4,0 checking the reserved bits are zero
+fn decode_vertical_intention(mb: &[u8; 7]) -> Option<VerticalIntention> {
+    if get_bits(mb, 40, 8) != 0 || get_bits(mb, 52, 2) != 0 {
+        return None;
+    }
+
+    let mcp_altitude = get_status_field(mb, 1, 12)?.map(|n| n as i32 * 16);
+    let fms_altitude = get_status_field(mb, 14, 12)?.map(|n| n as i32 * 16);
+    let baro_setting = get_status_field(mb, 27, 12)?.map(|n| n as f64 * 0.1 + 800.0);
+    get_status_field(mb, 48, 3)?;
+    get_status_field(mb, 54, 2)?;
+
+    if mcp_altitude.is_none() && fms_altitude.is_none() && baro_setting.is_none() {
+        return None;
+    }
+
+    if [mcp_altitude, fms_altitude].iter().flatten().any(|altitude| !(1..=50_000).contains(altitude)) {
+        return None;
+    }
+
+    if baro_setting.is_some_and(|baro| !(900.0..=1100.0).contains(&baro)) {
+        return None;
+    }
+
+    Some(VerticalIntention { mcp_altitude, fms_altitude, baro_setting })
+}
+
+/// Decode BDS 4,4 checking the source, wind and temperature are plausible
+fn decode_meteorological_routine(mb: &[u8; 7]) -> Option<MeteorologicalRoutine> {
+    if get_bits(mb, 1, 4) > 4 {
+        return None;
+    }
+
+    let wind = get_status_field(mb, 5, 18)?;
+    let temperature = to_signed(get_bits(mb, 24, 11), 11) as f64 * 0.25;
+    let pressure = get_status_field(mb, 35, 11)?.map(|n| n as f64);
+    let turbulence = get_status_field(mb, 47, 2)?.map(|n| n as u8);
+    let humidity = get_status_field(mb, 50, 6)?.map(|n| n as f64 * 100.0 / 64.0);
+
+    let wind_speed = wind.map(|wind| (wind >> 9) as f64);
+    let wind_direction = wind.map(|wind| (wind & 0x1FF) as f64 * 180.0 / 256.0);
+
+    if wind.is_none() || wind_speed.is_some_and(|speed| speed > 250.0) {
+        return None;
+    }
+
+    if !(-80.0..=60.0).contains(&temperature) {
+        return None;
+    }
+
+    if pressure.is_some_and(|pressure| pressure > 1100.0) {
+        return None;
+    }
+
+    Some(MeteorologicalRoutine { wind_speed, wind_direction, temperature, pressure, turbulence, humidity })
+}
+
+/// Decode BDS 4,5 checking the reserved bits are zero and the temperature is plausible
+fn decode_meteorological_hazard(mb: &[u8; 7]) -> Option<MeteorologicalHazard> {
+    if get_bits(mb, 52, 5) != 0 {
+        return None;
+    }
+
+    let turbulence = get_status_field(mb, 1, 2)?.map(|n| n as u8);
+    let wind_shear = get_status_field(mb, 4, 2)?.map(|n| n as u8);
+    let microburst = get_status_field(mb, 7, 2)?.map(|n| n as u8);
+    let icing = get_status_field(mb, 10, 2)?.map(|n| n as u8);
+    let wake_vortex = get_status_field(mb, 13, 2)?.map(|n| n as u8);
+    let temperature = get_status_field(mb, 16, 10)?.map(|n| to_signed(n, 10) as f64 * 0.25);
+    let pressure = get_status_field(mb, 27, 11)?.map(|n| n as f64);
+    let radio_height = get_status_field(mb, 39, 12)?.map(|n| n as i32 * 16);
+
+    if temperature.is_none() || temperature.is_some_and(|temperature| !(-80.0..=60.0).contains(&temperature)) {
+        return None;
+    }
+
+    if pressure.is_some_and(|pressure| pressure > 1100.0) {
+        return None;
+    }
+
+    Some(MeteorologicalHazard { turbulence, wind_shear, microburst, icing, wake_vortex, temperature, pressure, radio_height })
+}
+
+/// Decode BDS 5,0 checking the roll, speeds and their agreement are plausible
+fn decode_track_turn(mb: &[u8; 7]) -> Option<TrackTurn> {
+    let roll = get_status_field(mb, 1, 10)?.map(|n| to_signed(n, 10) as f64 * 45.0 / 256.0);
+    let true_track = get_status_field(mb, 12, 11)?.map(|n| (to_signed(n, 11) as f64 * 90.0 / 512.0).rem_euclid(360.0));
+    let ground_speed = get_status_field(mb, 24, 10)?.map(|n| n as f64 * 2.0);
+    let track_rate = get_status_field(mb, 35, 10)?.map(|n| to_signed(n, 10) as f64 * 8.0 / 256.0);
+    let true_airspeed = get_status_field(mb, 46, 10)?.map(|n| n as f64 * 2.0);
+
+    if roll.is_some_and(|roll| roll.abs() > 50.0) {
+        return None;
+    }
+
+    let (Some(ground_speed_kt), Some(true_airspeed_kt)) = (ground_speed, true_airspeed) else {
+        return None;
+    };
+
+    if ground_speed_kt > 600.0 || true_airspeed_kt > 500.0 || (ground_speed_kt - true_airspeed_kt).abs() > 200.0 {
+        return None;
+    }
+
+    Some(TrackTurn { roll, true_track, ground_speed, track_rate, true_airspeed })
+}
+
+/// Decode BDS 6,0 checking the speeds and vertical rates are plausible
+fn decode_heading_speed(mb: &[u8; 7]) -> Option<HeadingSpeed> {
+    let magnetic_heading = get_status_field(mb, 1, 11)?.map(|n| (to_signed(n, 11) as f64 * 90.0 / 512.0).rem_euclid(360.0));
+    let indicated_airspeed = get_status_field(mb, 13, 10)?.map(|n| n as f64);
+    let mach = get_status_field(mb, 24, 10)?.map(|n| n as f64 * 2.048 / 512.0);
+    let baro_vertical_rate = get_status_field(mb, 35, 10)?.map(|n| to_signed(n, 10) * 32);
+    let inertial_vertical_rate = get_status_field(mb, 46, 10)?.map(|n| to_signed(n, 10) * 32);
+
+    let (Some(indicated_airspeed_kt), Some(mach_number)) = (indicated_airspeed, mach) else {
+        return None;
+    };
+
+    if indicated_airspeed_kt == 0.0 || indicated_airspeed_kt > 500.0 || mach_number == 0.0 || mach_number > 1.0 {
+        return None;
+    }
+
+    if [baro_vertical_rate, inertial_vertical_rate].iter().flatten().any(|rate| rate.abs() > 6000) {
+        return None;
+    }
+
+    Some(HeadingSpeed { magnetic_heading, indicated_airspeed, mach, baro_vertical_rate, inertial_vertical_rate })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Convert the MB field of a hex string frame
+    fn mb(packet: &str) -> [u8; 7] {
+        let mut mb = [0u8; 7];
+        for (i, byte) in mb.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&packet[8 + i * 2..10 + i * 2], 16).unwrap();
+        }
+
+        mb
+    }
+
+    #[test]
+    fn test_identification() {
+        let register = CommB::infer(&[0x20, 0x2C, 0xC3, 0x71, 0xC3, 0x2C, 0xE0]);
+        assert_eq!(register, Some(CommB::Identification(String::from("KLM1023_"))));
+    }
+
+    #[test]
+    fn test_vertical_intention() {
+        let Some(CommB::VerticalIntention(intention)) = CommB::infer(&mb("A000029C85E42F313000007047D3")) else { panic!("Not BDS 4,0") };
+
+        assert_eq!(intention.mcp_altitude, Some(3008));
+        assert_eq!(intention.fms_altitude, Some(3008));
+        assert!((intention.baro_setting.unwrap() - 1020.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_track_turn() {
+        let Some(CommB::TrackTurn(track)) = CommB::infer(&mb("A000139381951536E024D4CCF6B5")) else { panic!("Not BDS 5,0") };
+
+        assert!((track.roll.unwrap() - 2.1).abs() < 0.1);
+        assert!((track.true_track.unwrap() - 114.258).abs() < 0.01);
+        assert_eq!(track.ground_speed, Some(438.0));
+        assert_eq!(track.track_rate, Some(0.125));
+        assert_eq!(track.true_airspeed, Some(424.0));
+    }
+
+    #[test]
+    fn test_heading_speed() {
+        let Some(CommB::HeadingSpeed(heading)) = CommB::infer(&mb("A00004128F39F91A7E27C46ADC21")) else { panic!("Not BDS 6,0") };
+
+        assert!((heading.magnetic_heading.unwrap() - 42.715).abs() < 0.01);
+        assert_eq!(heading.indicated_airspeed, Some(252.0));
+        assert!((heading.mach.unwrap() - 0.42).abs() < 0.001);
+        assert_eq!(heading.baro_vertical_rate, Some(-1920));
+        assert_eq!(heading.inertial_vertical_rate, Some(-1920));
+    }
+
+    #[test]
+    fn test_meteorological_routine() {
+        let Some(CommB::MeteorologicalRoutine(met)) = CommB::infer(&mb("A0001692185BD5CF400000DFC696")) else { panic!("Not BDS 4,4") };
+
+        assert_eq!(met.wind_speed, Some(22.0));
+        assert!((met.wind_direction.unwrap() - 344.5).abs() < 0.1);
+        assert_eq!(met.temperature, -48.75);
+    }
+
+    #[test]
+    fn test_infer_empty() {
+        assert_eq!(CommB::infer(&[0; 7]), None);
+        // Invalid characters in an identification register
+        assert_eq!(CommB::infer(&[0x20, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]), None);
+    }
+}
@@ -0,0 +1,372 @@
+/// Demodulate Mode S frames at any sample rate by correlating against the preamble
+///
+/// The frame timing is modelled in microseconds and each sample is weighted by how much of
+/// it falls inside a pulse. A small set of sub-sample phases is tried for every start sample
+/// and the phase that slices the frame most cleanly is kept.
+///
+/// Author: Jack Duignan (JackpDuignan@gmail.com)
+
+use crate::adsb::demod::{
+    check_packet, get_frame_bits, to_dbfs, DemodFrame, DemodStats, Demodulator,
+    LONG_FRAME_BITS, STRONG_SIGNAL_RATIO, SUPPORTED_DOWNLINK_FORMATS,
+};
+use crate::cli::ErrorCorrection;
+
+/// Length of the preamble in microseconds
+const PREAMBLE_US: f64 = 8.0;
+
+/// Preamble pulses in microseconds from the start of the frame
+const PREAMBLE_PULSES: [(f64, f64); 4] = [(0.0, 0.5), (1.0, 1.5), (3.5, 4.0), (4.5, 5.0)];
+
+/// Quiet parts of the preamble in microseconds from the start of the frame
+const PREAMBLE_GAPS: [(f64, f64); 4] = [(0.5, 1.0), (1.5, 3.5), (4.0, 4.5), (5.0, 8.0)];
+
+/// Largest step between the sub-sample phases that are tried in microseconds
+const PHASE_STEP_US: f64 = 0.1;
+
+/// Time past the first matching phase that is searched for a better one in microseconds,
+/// a preamble can't match once it is shifted by a pulse width
+const PHASE_SEARCH_US: f64 = 1.0;
+
+/// Number of bits holding the downlink format
+const DOWNLINK_FORMAT_BITS: usize = 5;
+
+/// The samples covering a time interval and the fraction of each sample inside it
+#[derive(Debug, Clone, PartialEq)]
+struct Span {
+    /// Offset of the first sample from the start of the preamble
+    first: usize,
+    weights: Vec<f64>,
+}
+
+impl Span {
+    /// Create a span covering a time interval
+    ///
+    /// `phase` - the offset of the preamble start in samples
+    /// `start_us` - the start of the interval in microseconds
+    /// `end_us` - the end of the interval in microseconds
+    /// `samples_per_us` - the number of samples per microsecond
+    fn new(phase: f64, start_us: f64, end_us: f64, samples_per_us: f64) -> Self {
+        let start = phase + start_us * samples_per_us;
+        let end = phase + end_us * samples_per_us;
+        let first = start.floor() as usize;
+
+        let weights = (first..end.ceil() as usize)
+            .map(|i| (end.min(i as f64 + 1.0) - start.max(i as f64)).max(0.0))
+            .collect();
+
+        Span {
+            first: first,
+            weights: weights,
+        }
+    }
+
+    /// Get the weighted mean magnitude of the span
+    ///
+    /// `mags` - the magnitude buffer
+    /// `start` - the sample holding the start of the preamble
+    fn mean(&self, mags: &[u32], start: usize) -> f64 {
+        let samples = &mags[start + self.first..start + self.first + self.weights.len()];
+        let total = self.weights.iter().sum::<f64>();
+
+        samples.iter()
+            .zip(self.weights.iter())
+            .map(|(mag, weight)| *mag as f64 * weight)
+            .sum::<f64>() / total
+    }
+}
+
+/// A frame sliced at one phase
+#[derive(Debug, Clone)]
+struct Candidate {
+    packet: Vec<u8>,
+    /// Sum of the difference between the two halves of every bit
+    confidence: f64,
+    signal_power: f64,
+    noise_power: f64,
+    strong_signal: bool,
+    length: usize,
+}
+
+/// The spans of the preamble and every bit for one sub-sample phase
+#[derive(Debug, Clone)]
+struct PhaseTemplate {
+    phase: f64,
+    pulses: Vec<Span>,
+    gaps: Vec<Span>,
+    /// The first and second half of each bit
+    bits: Vec<(Span, Span)>,
+}
+
+impl PhaseTemplate {
+    fn new(phase: f64, samples_per_us: f64) -> Self {
+        let to_spans = |intervals: &[(f64, f64)]| intervals.iter()
+            .map(|(start, end)| Span::new(phase, *start, *end, samples_per_us))
+            .collect();
+
+        let bits = (0..LONG_FRAME_BITS)
+            .map(|bit| {
+                let start = PREAMBLE_US + bit as f64;
+                (Span::new(phase, start, start + 0.5, samples_per_us),
+                 Span::new(phase, start + 0.5, start + 1.0, samples_per_us))
+            })
+            .collect();
+
+        PhaseTemplate {
+            phase: phase,
+            pulses: to_spans(&PREAMBLE_PULSES),
+            gaps: to_spans(&PREAMBLE_GAPS),
+            bits: bits,
+        }
+    }
+
+    /// Check if every preamble pulse is above every gap at this phase
+    ///
+    /// `mags` - the magnitude buffer
+    /// `start` - the sample holding the start of the preamble
+    ///
+    /// returns the weakest pulse and the level of each gap
+    fn match_preamble(&self, mags: &[u32], start: usize) -> Option<(f64, Vec<f64>)> {
+        let min_pulse = self.pulses.iter().map(|span| span.mean(mags, start)).fold(f64::MAX, f64::min);
+        let gaps: Vec<f64> = self.gaps.iter().map(|span| span.mean(mags, start)).collect();
+
+        if gaps.iter().any(|gap| min_pulse <= *gap) {
+            return None;
+        }
+
+        Some((min_pulse, gaps))
+    }
+
+    /// Slice a frame at this phase if the preamble matches
+    ///
+    /// `mags` - the magnitude buffer
+    /// `start` - the sample holding the start of the preamble
+    /// `samples_per_us` - the number of samples per microsecond
+    fn slice(&self, mags: &[u32], start: usize, samples_per_us: f64) -> Option<Candidate> {
+        let (min_pulse, gaps) = self.match_preamble(mags, start)?;
+        let max_gap = gaps.iter().cloned().fold(0.0, f64::max);
+
+        let mut downlink_format = 0;
+        for (first, second) in self.bits[0..DOWNLINK_FORMAT_BITS].iter() {
+            downlink_format <<= 1;
+            if first.mean(mags, start) > second.mean(mags, start) {
+                downlink_format |= 1;
+            }
+        }
+
+        if !SUPPORTED_DOWNLINK_FORMATS.contains(&downlink_format) {
+            return None;
+        }
+
+        let frame_bits = get_frame_bits(downlink_format);
+        let mut packet = vec![0u8; frame_bits / 8];
+        let mut confidence = 0.0;
+        let mut signal_power = 0.0;
+
+        for (bit, (first, second)) in self.bits[0..frame_bits].iter().enumerate() {
+            let first = first.mean(mags, start);
+            let second = second.mean(mags, start);
+
+            if first > second {
+                packet[bit / 8] |= 1 << (7 - bit % 8);
+            }
+            confidence += (first - second).abs();
+            signal_power += first.max(second).powi(2);
+        }
+
+        let noise_power = gaps.iter().map(|gap| gap.powi(2)).sum::<f64>() / gaps.len() as f64;
+
+        Some(Candidate {
+            packet: packet,
+            confidence: confidence,
+            signal_power: to_dbfs(signal_power / frame_bits as f64),
+            noise_power: to_dbfs(noise_power),
+            strong_signal: min_pulse >= max_gap * STRONG_SIGNAL_RATIO as f64,
+            length: (self.phase + (PREAMBLE_US + frame_bits as f64) * samples_per_us).ceil() as usize,
+        })
+    }
+}
+
+/// Demodulator that correlates the preamble at any sample rate
+#[derive(Debug, Clone)]
+pub struct CorrelationDemod {
+    samples_per_us: f64,
+    /// Number of phases in one sample, these are used to find a preamble
+    phases_per_sample: usize,
+    /// Number of samples the phases are spread over
+    phase_samples: usize,
+    phases: Vec<PhaseTemplate>,
+}
+
+impl CorrelationDemod {
+    /// Create a demodulator for the given sample rate
+    ///
+    /// `sample_rate` - the sample rate in Hz, at least 2 MS/s
+    pub fn new(sample_rate: f64) -> Self {
+        let samples_per_us = sample_rate / 1e6;
+        assert!(samples_per_us >= 2.0, "The correlation demodulator needs at least 2 MS/s");
+
+        // Step through a sample finely enough that one phase lands close to every pulse edge.
+        // A preamble is found at the first phase it matches which is at the edge of what
+        // matches, so the phases run on past the start sample to find the best one.
+        let phases_per_sample = (1.0 / (samples_per_us * PHASE_STEP_US)).ceil().max(1.0) as usize;
+        let phase_samples = 1 + (PHASE_SEARCH_US * samples_per_us).ceil() as usize;
+        let phases = (0..phases_per_sample * phase_samples)
+            .map(|i| PhaseTemplate::new(i as f64 / phases_per_sample as f64, samples_per_us))
+            .collect();
+
+        CorrelationDemod {
+            samples_per_us: samples_per_us,
+            phases_per_sample: phases_per_sample,
+            phase_samples: phase_samples,
+            phases: phases,
+        }
+    }
+}
+
+impl Demodulator for CorrelationDemod {
+    fn get_window(&self) -> usize {
+        ((PREAMBLE_US + LONG_FRAME_BITS as f64) * self.samples_per_us).ceil() as usize + self.phase_samples + 1
+    }
+
+    fn demodulate(&self, mags: &[u32], start: usize, correction: ErrorCorrection, stats: &mut DemodStats) -> Option<DemodFrame> {
+        if !self.phases[0..self.phases_per_sample].iter().any(|phase| phase.match_preamble(mags, start).is_some()) {
+            return None;
+        }
+
+        let mut candidates: Vec<Candidate> = self.phases.iter()
+            .filter_map(|phase| phase.slice(mags, start, self.samples_per_us))
+            .collect();
+
+        if candidates.is_empty() {
+            return None;
+        }
+        stats.preambles += 1;
+
+        // Prefer the cleanest phase that is correct as is before trying to correct the best one
+        candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+        let (candidate, (packet, corrected_bits)) = candidates.iter()
+            .find_map(|c| check_packet(c.packet.clone(), ErrorCorrection::Off, false).map(|r| (c, r)))
+            .or_else(|| {
+                let best = &candidates[0];
+                check_packet(best.packet.clone(), correction, best.strong_signal).map(|r| (best, r))
+            })?;
+
+        Some(DemodFrame {
+            packet: packet,
+            corrected_bits: corrected_bits,
+            signal_power: candidate.signal_power,
+            noise_power: candidate.noise_power,
+            length: candidate.length,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adsb::fixtures::FRAME;
+
+    /// Sample a frame at any rate by averaging the ideal pulse train over each sample
+    ///
+    /// `packet` - the frame to send
+    /// `sample_rate` - the sample rate in Hz
+    /// `offset` - the position of the preamble start in samples
+    fn to_samples(packet: &[u8], sample_rate: f64, offset: f64) -> Vec<u32> {
+        const OVERSAMPLE: usize = 64;
+        let samples_per_us = sample_rate / 1e6;
+        let len = offset.ceil() as usize + ((PREAMBLE_US + packet.len() as f64 * 8.0 + 4.0) * samples_per_us) as usize;
+
+        let level = |t: f64| {
+            let high = if t < 0.0 {
+                false
+            } else if t < PREAMBLE_US {
+                PREAMBLE_PULSES.iter().any(|(start, end)| t >= *start && t < *end)
+            } else {
+                let bit = (t - PREAMBLE_US) as usize;
+                let first_half = (t - PREAMBLE_US).fract() < 0.5;
+                match packet.get(bit / 8) {
+                    Some(byte) => ((byte >> (7 - bit % 8)) & 1 == 1) == first_half,
+                    None => false,
+                }
+            };
+            if high { 1000.0 } else { 40.0 }
+        };
+
+        (0..len)
+            .map(|i| {
+                let total: f64 = (0..OVERSAMPLE)
+                    .map(|j| level((i as f64 + (j as f64 + 0.5) / OVERSAMPLE as f64 - offset) / samples_per_us))
+                    .sum();
+                (total / OVERSAMPLE as f64) as u32
+            })
+            .collect()
+    }
+
+    /// Run the demodulator over a buffer returning the first frame found
+    fn find_frame(demod: &CorrelationDemod, mags: &[u32], correction: ErrorCorrection) -> Option<(usize, DemodFrame)> {
+        let mut stats = DemodStats::default();
+        (0..mags.len() - demod.get_window())
+            .find_map(|i| demod.demodulate(mags, i, correction, &mut stats).map(|frame| (i, frame)))
+    }
+
+    #[test]
+    fn test_span_weights() {
+        let span = Span::new(0.25, 0.5, 1.0, 2.4);
+        assert_eq!(span.first, 1);
+        assert_eq!(span.weights.len(), 2);
+        assert!((span.weights.iter().sum::<f64>() - 1.2).abs() < 1e-9);
+        assert!((span.weights[0] - 0.55).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_demodulate_2_4_msps() {
+        let demod = CorrelationDemod::new(2_400_000.0);
+        for offset in [10.0, 10.3, 10.5, 10.8] {
+            let mags = to_samples(&FRAME, 2_400_000.0, offset);
+            let (start, frame) = find_frame(&demod, &mags, ErrorCorrection::Off).expect("No frame found");
+
+            // The frame ends where the best phase puts it not where the preamble first matched
+            let end = offset + (PREAMBLE_US + 112.0) * 2.4;
+            assert!((start + frame.length) as f64 >= end);
+            assert!((start + frame.length) as f64 - end < 1.0, "offset {} ends at {}", offset, start + frame.length);
+            assert_eq!(frame.packet, FRAME.to_vec());
+            assert_eq!(frame.corrected_bits, 0);
+            assert!(frame.signal_power > frame.noise_power);
+        }
+    }
+
+    #[test]
+    fn test_demodulate_other_rates() {
+        for sample_rate in [2_000_000.0, 3_200_000.0, 8_000_000.0] {
+            let demod = CorrelationDemod::new(sample_rate);
+            let mags = to_samples(&FRAME, sample_rate, 5.6);
+            let (_, frame) = find_frame(&demod, &mags, ErrorCorrection::Off).expect("No frame found");
+
+            assert_eq!(frame.packet, FRAME.to_vec());
+            assert!(frame.length <= demod.get_window());
+        }
+    }
+
+    #[test]
+    fn test_demodulate_correction() {
+        let mut corrupted = FRAME;
+        corrupted[6] ^= 0x04;
+        let demod = CorrelationDemod::new(2_400_000.0);
+        let mags = to_samples(&corrupted, 2_400_000.0, 3.4);
+
+        assert!(find_frame(&demod, &mags, ErrorCorrection::Off).is_none());
+        let (_, frame) = find_frame(&demod, &mags, ErrorCorrection::OneBit).expect("No frame found");
+        assert_eq!(frame.packet, FRAME.to_vec());
+        assert_eq!(frame.corrected_bits, 1);
+    }
+
+    #[test]
+    fn test_demodulate_noise() {
+        let demod = CorrelationDemod::new(2_400_000.0);
+        let mags: Vec<u32> = (0..1000u32).map(|i| 40 + (i.wrapping_mul(2_654_435_761) >> 26)).collect();
+
+        assert!(find_frame(&demod, &mags, ErrorCorrection::OneBit).is_none());
+    }
+}
@@ -16,6 +16,57 @@ pub struct GeographicPosition {
 }
 
 
+impl GeographicPosition {
+    /// Calculate the great circle distance to another position
+    ///
+    /// `other` - the position to measure to
+    ///
+    /// returns the distance in nautical miles
+    pub fn distance_nm(&self, other: &GeographicPosition) -> f64 {
+        let lat1 = self.latitude.to_radians();
+        let lat2 = other.latitude.to_radians();
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+
+        2.0 * EARTH_RADIUS_NM * a.sqrt().asin()
+    }
+
+    /// Calculate the initial great circle bearing to another position
+    ///
+    /// `other` - the position to head towards
+    ///
+    /// returns the bearing in degrees clockwise from true north
+    pub fn bearing_deg(&self, other: &GeographicPosition) -> f64 {
+        let lat1 = self.latitude.to_radians();
+        let lat2 = other.latitude.to_radians();
+        let d_lon = (other.longitude - self.longitude).to_radians();
+
+        let y = d_lon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * d_lon.cos();
+
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+
+    /// Calculate the distance from the great circle through two positions
+    ///
+    /// `start` - the start of the path
+    /// `end` - the end of the path
+    ///
+    /// returns the unsigned cross track distance in nautical miles
+    pub fn cross_track_nm(&self, start: &GeographicPosition, end: &GeographicPosition) -> f64 {
+        let distance = start.distance_nm(self) / EARTH_RADIUS_NM;
+        let angle = (start.bearing_deg(self) - start.bearing_deg(end)).to_radians();
+
+        (distance.sin() * angle.sin()).asin().abs() * EARTH_RADIUS_NM
+    }
+}
+
+/// Mean radius of the earth in nautical miles
+const EARTH_RADIUS_NM: f64 = 3440.065;
+
+
 const NUM_ZONES: f64 = 15.0;
 
 /// Convert a CPR value to a float
@@ -70,8 +121,8 @@ fn calculate_latitude(even_cpr_lat: u32, odd_cpr_lat: u32, first: CprFormat) ->
 
     let latitude_index: f64 = (59.0*even_cpr_lat - 60.0 * odd_cpr_lat + 0.5).floor();
 
-    let even_latitude = EVEN_LAT_DIVISIONS * (latitude_index % 60.0 + even_cpr_lat);
-    let odd_latitude = ODD_LAT_DIVISIONS * (latitude_index % 59.0 + odd_cpr_lat);
+    let even_latitude = EVEN_LAT_DIVISIONS * (latitude_index.rem_euclid(60.0) + even_cpr_lat);
+    let odd_latitude = ODD_LAT_DIVISIONS * (latitude_index.rem_euclid(59.0) + odd_cpr_lat);
 
     let mut latitude = match first {
         // Use the newest format to determine the latitude
@@ -87,6 +138,17 @@ fn calculate_latitude(even_cpr_lat: u32, odd_cpr_lat: u32, first: CprFormat) ->
     
 }
 
+/// Calculate the longitude from the even and odd CPR longitudes
+///
+/// The newest frame sets the number of zones, NL(lat) for even and NL(lat) - 1 for odd.
+/// The zone index is wrapped with a euclidean remainder so it is never negative.
+///
+/// `even_cpr_long` - the even CPR longitude
+/// `odd_cpr_long` - the odd CPR longitude
+/// `latitude` - the latitude decoded from the pair
+/// `first` - the first CPR format recieved (oldest)
+///
+/// returns the longitude in degrees
 fn calculate_longitude(even_cpr_long: u32, odd_cpr_long: u32, latitude: f64, first: CprFormat) -> f64 {
     let lon_cpr_e = convert_cpr_to_float(even_cpr_long);
     let lon_cpr_o = convert_cpr_to_float(odd_cpr_long);
@@ -98,26 +160,26 @@ fn calculate_longitude(even_cpr_long: u32, odd_cpr_long: u32, latitude: f64, fir
     match first {
         CprFormat::Even => {
             // Later is odd
-            num_zones = (calc_num_zones(latitude-1.0)).max(1) as f64
+            num_zones = (nl.saturating_sub(1)).max(1) as f64
         },
         CprFormat::Odd => {
             // Later is even
-            num_zones = (calc_num_zones(latitude)).max(1) as f64
+            num_zones = nl.max(1) as f64
         },
     }
 
     
     let divisions = 360.0 / num_zones;
-    let m = (lon_cpr_e * ((nl - 1) as f64) - lon_cpr_o * (nl as f64) + 0.5).floor();
+    let m = (lon_cpr_e * (nl.saturating_sub(1) as f64) - lon_cpr_o * (nl as f64) + 0.5).floor();
 
     let mut longitude: f64;
 
     match first {
         CprFormat::Even => {
-            longitude = divisions * ((m % num_zones) + lon_cpr_o);
+            longitude = divisions * (m.rem_euclid(num_zones) + lon_cpr_o);
         },
         CprFormat::Odd => {
-            longitude = divisions * ((m % num_zones) + lon_cpr_e);
+            longitude = divisions * (m.rem_euclid(num_zones) + lon_cpr_e);
         },
     }
      
@@ -146,9 +208,180 @@ pub fn calculate_geographic_position(even_cpr_lat_long: (u32, u32), odd_cpr_lat_
     Some(GeographicPosition { latitude: latitude, longitude: longitude })
 }
 
+/// Calculate the geographic position of a surface aircraft from the even and odd CPR positions
+///
+/// Surface positions are encoded over 90 degrees so the reference position is used
+/// to select the correct quadrant.
+///
+/// `even_cpr_lat_long` - the latitude and longitude from the even CPR packet
+/// `odd_cpr_lat_long` - the latitude and longitude from the odd CPR packet
+/// `first` - the first CPR format (even or odd)
+/// `reference` - a position within 45 degrees of the aircraft
+///
+/// returns a GeographicPosition if it can be calculated, otherwise None
+pub fn calculate_surface_position(even_cpr_lat_long: (u32, u32), odd_cpr_lat_long: (u32, u32), first: CprFormat, reference: &GeographicPosition) -> Option<GeographicPosition> {
+    const EVEN_LAT_DIVISIONS: f64 = 90.0 / (4.0 * NUM_ZONES);
+    const ODD_LAT_DIVISIONS: f64 = 90.0 / (4.0 * NUM_ZONES - 1.0);
+
+    let even_cpr_lat = convert_cpr_to_float(even_cpr_lat_long.0);
+    let odd_cpr_lat = convert_cpr_to_float(odd_cpr_lat_long.0);
+
+    let latitude_index = (59.0 * even_cpr_lat - 60.0 * odd_cpr_lat + 0.5).floor();
+
+    // Each latitude is either in the northern solution or 90 degrees south of it
+    let closest_latitude = |lat: f64| {
+        if (lat - reference.latitude).abs() <= (lat - 90.0 - reference.latitude).abs() { lat } else { lat - 90.0 }
+    };
+    let even_latitude = closest_latitude(EVEN_LAT_DIVISIONS * (latitude_index.rem_euclid(60.0) + even_cpr_lat));
+    let odd_latitude = closest_latitude(ODD_LAT_DIVISIONS * (latitude_index.rem_euclid(59.0) + odd_cpr_lat));
+
+    if calc_num_zones(even_latitude) != calc_num_zones(odd_latitude) {
+        return None;
+    }
+
+    let latitude = match first {
+        CprFormat::Even => odd_latitude,
+        CprFormat::Odd => even_latitude,
+    };
+
+    let lon_cpr_e = convert_cpr_to_float(even_cpr_lat_long.1);
+    let lon_cpr_o = convert_cpr_to_float(odd_cpr_lat_long.1);
+    let nl = calc_num_zones(latitude);
+    let m = (lon_cpr_e * (nl.saturating_sub(1) as f64) - lon_cpr_o * (nl as f64) + 0.5).floor();
+
+    let (num_zones, lon_cpr) = match first {
+        CprFormat::Even => (nl.saturating_sub(1).max(1) as f64, lon_cpr_o),
+        CprFormat::Odd => (nl.max(1) as f64, lon_cpr_e),
+    };
+
+    let longitude = 90.0 / num_zones * (m.rem_euclid(num_zones) + lon_cpr);
+
+    // Select the quadrant closest to the reference
+    let longitude = (0..4)
+        .map(|quadrant| normalize_longitude(longitude + 90.0 * quadrant as f64))
+        .min_by(|a, b| longitude_difference(*a, reference.longitude)
+            .total_cmp(&longitude_difference(*b, reference.longitude)))?;
+
+    Some(GeographicPosition { latitude: latitude, longitude: longitude })
+}
+
+/// Calculate the geographic position from a single CPR frame relative to a reference position
+///
+/// The result is only correct if the aircraft is within half a zone of the reference
+/// (180 NM airborne or 45 NM on the surface).
+///
+/// `cpr_lat_long` - the latitude and longitude from the CPR packet
+/// `format` - the CPR format of the packet
+/// `reference` - the reference position (last known position or receiver location)
+/// `surface` - true if the packet is a surface position
+///
+/// returns a GeographicPosition if it can be calculated, otherwise None
+pub fn calculate_local_position(cpr_lat_long: (u32, u32), format: CprFormat, reference: &GeographicPosition, surface: bool) -> Option<GeographicPosition> {
+    let span = if surface { 90.0 } else { 360.0 };
+    let odd = match format {
+        CprFormat::Even => 0,
+        CprFormat::Odd => 1,
+    };
+
+    let cpr_lat = convert_cpr_to_float(cpr_lat_long.0);
+    let cpr_lon = convert_cpr_to_float(cpr_lat_long.1);
+
+    let lat_divisions = span / (4.0 * NUM_ZONES - odd as f64);
+    let latitude_index = (reference.latitude / lat_divisions).floor()
+        + (reference.latitude.rem_euclid(lat_divisions) / lat_divisions - cpr_lat + 0.5).floor();
+    let latitude = lat_divisions * (latitude_index + cpr_lat);
+
+    if !(-90.0..=90.0).contains(&latitude) {
+        return None;
+    }
+
+    let num_zones = calc_num_zones(latitude).saturating_sub(odd).max(1) as f64;
+    let lon_divisions = span / num_zones;
+    let longitude_index = (reference.longitude / lon_divisions).floor()
+        + (reference.longitude.rem_euclid(lon_divisions) / lon_divisions - cpr_lon + 0.5).floor();
+    let longitude = normalize_longitude(lon_divisions * (longitude_index + cpr_lon));
+
+    Some(GeographicPosition { latitude: latitude, longitude: longitude })
+}
+
+/// Find the absolute difference between two longitudes in degrees
+fn longitude_difference(a: f64, b: f64) -> f64 {
+    normalize_longitude(a - b).abs()
+}
+
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_distance_nm() {
+        let wellington = GeographicPosition { latitude: -41.327, longitude: 174.805 };
+        let auckland = GeographicPosition { latitude: -37.008, longitude: 174.792 };
+
+        assert!((wellington.distance_nm(&auckland) - 259.1).abs() < 0.5);
+        assert_eq!(wellington.distance_nm(&wellington), 0.0);
+    }
+
+    #[test]
+    fn test_bearing_deg() {
+        let origin = GeographicPosition { latitude: 0.0, longitude: 0.0 };
+
+        assert!((origin.bearing_deg(&GeographicPosition { latitude: 1.0, longitude: 0.0 }) - 0.0).abs() < 0.001);
+        assert!((origin.bearing_deg(&GeographicPosition { latitude: 0.0, longitude: 1.0 }) - 90.0).abs() < 0.001);
+        assert!((origin.bearing_deg(&GeographicPosition { latitude: 0.0, longitude: -1.0 }) - 270.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_cross_track_nm() {
+        let start = GeographicPosition { latitude: 0.0, longitude: 0.0 };
+        let end = GeographicPosition { latitude: 0.0, longitude: 2.0 };
+
+        // A degree of latitude is 60 nautical miles
+        let north = GeographicPosition { latitude: 0.5, longitude: 1.0 };
+        assert!((north.cross_track_nm(&start, &end) - 30.0).abs() < 0.1);
+
+        let south = GeographicPosition { latitude: -0.5, longitude: 1.0 };
+        assert!((south.cross_track_nm(&start, &end) - 30.0).abs() < 0.1);
+
+        let on_path = GeographicPosition { latitude: 0.0, longitude: 1.5 };
+        assert!(on_path.cross_track_nm(&start, &end) < 0.001);
+    }
+
+    #[test]
+    fn test_geographic_position_odd_latest() {
+        // Reference pair from The 1090 Megahertz Riddle (J. Sun) with the odd frame newest
+        let position = calculate_geographic_position((93000, 51372), (74158, 50194), CprFormat::Even).unwrap();
+
+        assert!((position.latitude - 52.26578).abs() < 0.0001);
+        assert!((position.longitude - 3.93891).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_local_position_calculation() {
+        let reference = GeographicPosition { latitude: 52.258, longitude: 3.918 };
+
+        let position = calculate_local_position((93000, 51372), CprFormat::Even, &reference, false).unwrap();
+        assert!((position.latitude - 52.25720).abs() < 0.0001);
+        assert!((position.longitude - 3.91937).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_local_position_calculation_surface() {
+        let reference = GeographicPosition { latitude: 51.990, longitude: 4.375 };
+
+        let position = calculate_local_position((39199, 110269), CprFormat::Odd, &reference, true).unwrap();
+        assert!((position.latitude - 52.32061).abs() < 0.0001);
+        assert!((position.longitude - 4.73473).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_surface_position_calculation() {
+        let reference = GeographicPosition { latitude: 51.990, longitude: 4.375 };
+
+        let position = calculate_surface_position((115609, 116941), (39199, 110269), CprFormat::Even, &reference).unwrap();
+        assert!((position.latitude - 52.32061).abs() < 0.0001);
+        assert!((position.longitude - 4.73473).abs() < 0.0001);
+    }
+
     #[test]
     fn test_latitude_calculation() {
         let even_cpr_lat = 93000; // Example even CPR latitude
@@ -184,7 +417,16 @@ mod tests {
         let first = CprFormat::Odd;
 
         let longitude = calculate_longitude(even_cpr_long, odd_cpr_long, latitude, first);
-        assert!((longitude -  3.829498291015625).abs() < 0.0001); // Adjust expected value based on actual calculation
+        // Reference pair from The 1090 Megahertz Riddle (J. Sun), airborne position chapter
+        assert!((longitude -  3.91937255859375).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_longitude_calculation_polar() {
+        // Above 87 degrees there is a single zone, NL - 1 must not underflow
+        let longitude = calculate_longitude(51372, 50194, 88.0, CprFormat::Even);
+
+        assert!((longitude - 360.0 * convert_cpr_to_float(50194)).abs() < 0.0001);
     }
 
     #[test]
@@ -1,65 +1,218 @@
 /// Handle crc calculation of packets
-/// 
+///
 /// Author: Jack Duignan (JackpDuignan@gmail.com)
 
-/// Get the crc 
-/// 
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Mode S parity generator polynomial without the leading x^24 term
+const GENERATOR: u32 = 0xFF_F409;
+
+/// Remainder of each possible leading byte used to process a byte at a time
+static CRC_TABLE: [u32; 256] = build_crc_table();
+
+/// Build the byte-wise crc lookup table
+const fn build_crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = (i as u32) << 16;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc <<= 1;
+            if crc & 0x100_0000 != 0 {
+                crc ^= GENERATOR;
+            }
+            bit += 1;
+        }
+
+        table[i] = crc & 0xFF_FFFF;
+        i += 1;
+    }
+
+    table
+}
+
+/// Get the crc
+///
 /// `buf` - the data buffer (no crc)
-/// 
+///
 /// returns the crc calculated from the data
-pub fn get_adsb_crc(buf: &Vec<u8>) -> u32 {
-    const GENERATOR: u32 = 0b1_1111_1111_1111_0100_0000_1001;
-    const GENERATOR_LEN: usize = 24;
+pub fn get_adsb_crc(buf: &[u8]) -> u32 {
+    let mut crc = 0u32;
 
-    // convert buf into a vector of bits
-    let mut bits = Vec::with_capacity(buf.len() * 8 + GENERATOR_LEN);
     for byte in buf {
-        for i in (0..8).rev() {
-            bits.push((byte >> i) & 1 != 0);
-        }
+        crc = ((crc << 8) ^ CRC_TABLE[(((crc >> 16) as u8) ^ byte) as usize]) & 0xFF_FFFF;
     }
 
-    bits.extend(std::iter::repeat(false).take(GENERATOR_LEN));
+    crc
+}
 
-    for i in 0..(bits.len() - GENERATOR_LEN) {
-        if bits[i] {
-            for j in 0..=GENERATOR_LEN {
-                bits[i + j] ^= ((GENERATOR >> (GENERATOR_LEN - j)) & 1) != 0;
+/// Get the syndrome of a packet, the crc of the data XORed with the parity field
+///
+/// `buf` - the full packet including the 24 bit parity field
+///
+/// returns zero for an error free packet or the value overlaid on the parity such as
+/// the interrogator code of an all call reply or the address of a surveillance reply
+pub fn get_syndrome(buf: &[u8]) -> u32 {
+    let len = buf.len();
+    let parity = (buf[len-3] as u32) << 16 | (buf[len-2] as u32) << 8 | buf[len-1] as u32;
+
+    get_adsb_crc(&buf[0..len-3]) ^ parity
+}
+
+/// Build the table of bit positions that produce each single and double bit error syndrome
+///
+/// Syndromes shared by more than one double bit error are left out as they can't be corrected.
+///
+/// `frame_bits` - the number of bits in the frame
+fn build_syndrome_table(frame_bits: usize) -> HashMap<u32, Vec<usize>> {
+    // The crc is linear so the syndrome of an error is the XOR of the syndromes of its bits
+    let bit_syndromes: Vec<u32> = (0..frame_bits)
+        .map(|bit| {
+            let mut frame = vec![0u8; frame_bits / 8];
+            frame[bit / 8] = 1 << (7 - bit % 8);
+            get_syndrome(&frame)
+        })
+        .collect();
+
+    let mut table = HashMap::new();
+    let mut ambiguous = Vec::new();
+
+    for (first, first_syndrome) in bit_syndromes.iter().enumerate() {
+        table.insert(*first_syndrome, vec![first]);
+    }
+
+    for (first, first_syndrome) in bit_syndromes.iter().enumerate() {
+        for (second, second_syndrome) in bit_syndromes.iter().enumerate().skip(first + 1) {
+            let syndrome = first_syndrome ^ second_syndrome;
+
+            match table.get(&syndrome) {
+                Some(bits) if bits.len() == 1 => (),
+                Some(_) => ambiguous.push(syndrome),
+                None => { table.insert(syndrome, vec![first, second]); },
             }
         }
     }
 
-    let mut remainder = 0u32;
-    for i in 0..GENERATOR_LEN {
-        if bits[bits.len() - GENERATOR_LEN + i] {
-            remainder |= 1 << (GENERATOR_LEN - 1 - i);
-        }
+    for syndrome in ambiguous {
+        table.remove(&syndrome);
     }
 
-    remainder
+    table
+}
+
+/// Get the bits that need to be flipped to correct a frame
+///
+/// `syndrome` - the syndrome of the frame
+/// `frame_bits` - the number of bits in the frame (56 or 112)
+///
+/// returns the bit positions counted from the first bit of the frame or None if the
+/// error is not a correctable single or double bit error
+pub fn get_error_bits(syndrome: u32, frame_bits: usize) -> Option<&'static [usize]> {
+    static SHORT_SYNDROMES: OnceLock<HashMap<u32, Vec<usize>>> = OnceLock::new();
+    static LONG_SYNDROMES: OnceLock<HashMap<u32, Vec<usize>>> = OnceLock::new();
+
+    let table = match frame_bits {
+        56 => SHORT_SYNDROMES.get_or_init(|| build_syndrome_table(56)),
+        112 => LONG_SYNDROMES.get_or_init(|| build_syndrome_table(112)),
+        _ => return None,
+    };
+
+    table.get(&syndrome).map(|bits| bits.as_slice())
 }
 
+/// Number of bits at the start of a frame that are never corrected, the downlink format
+/// and capability follow the preamble where timing errors are most likely and a wrong
+/// downlink format would change how the rest of the frame is read
+const PROTECTED_BITS: usize = 8;
 
 /// Attempt to augment the packet to match the crc
-/// 
+///
 /// `buf` - the buffer to manipulate
-/// `calc_crc` - the calculated crc
-/// `packet_crc` - the crc from the packet
-/// return a augmented buffer if succesful
-pub fn try_crc_recovery(buf: Vec<u8>, _calc_crc: u32, packet_crc: u32) -> Option<Vec<u8>> {
-    for (num, byte) in buf.iter().enumerate() {
-        let mut augmented_buf = buf.clone();
-        for i in 0..8 {
-            let mut augmented_byte = *byte;
-            augmented_byte ^= 1 << (7-i); // flip the i-th bit
-            augmented_buf[num] = augmented_byte;
-            let crc = get_adsb_crc(&augmented_buf[0..augmented_buf.len()-3].to_vec());
-
-            if crc == packet_crc {
-                return Some(augmented_buf);
+/// `max_errors` - the maximum number of bits that can be flipped
+/// return a augmented buffer and the number of bits flipped if succesful
+pub fn try_crc_recovery(mut buf: Vec<u8>, max_errors: usize) -> Option<(Vec<u8>, usize)> {
+    let error_bits = get_error_bits(get_syndrome(&buf), buf.len() * 8)?;
+
+    if error_bits.len() > max_errors || error_bits.iter().any(|bit| *bit < PROTECTED_BITS) {
+        return None;
+    }
+
+    for bit in error_bits {
+        buf[bit / 8] ^= 1 << (7 - bit % 8);
+    }
+
+    Some((buf, error_bits.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adsb::fixtures::{get_adsb_crc_bitwise, FRAME};
+
+    #[test]
+    fn test_crc_matches_bitwise() {
+        let mut data = [0u8; 11];
+        for seed in 0..500u32 {
+            for (i, byte) in data.iter_mut().enumerate() {
+                *byte = (seed.wrapping_mul(2_654_435_761).rotate_left(i as u32 * 3) >> 8) as u8;
+            }
+
+            assert_eq!(get_adsb_crc(&data), get_adsb_crc_bitwise(&data));
+            assert_eq!(get_adsb_crc(&data[0..4]), get_adsb_crc_bitwise(&data[0..4]));
+        }
+    }
+
+    #[test]
+    fn test_syndrome() {
+        assert_eq!(get_syndrome(&FRAME), 0);
+    }
+
+    #[test]
+    fn test_crc_recovery_single() {
+        for bit in 0..112 {
+            let mut corrupted = FRAME.to_vec();
+            corrupted[bit / 8] ^= 1 << (7 - bit % 8);
+
+            assert_eq!(get_error_bits(get_syndrome(&corrupted), 112), Some(&[bit][..]));
+            if bit < PROTECTED_BITS {
+                assert_eq!(try_crc_recovery(corrupted, 1), None);
+            } else {
+                assert_eq!(try_crc_recovery(corrupted, 1), Some((FRAME.to_vec(), 1)));
             }
         }
     }
 
-    None
-}
\ No newline at end of file
+    #[test]
+    fn test_crc_recovery_double() {
+        let mut corrupted = FRAME.to_vec();
+        corrupted[2] ^= 0x10;
+        corrupted[9] ^= 0x01;
+
+        assert_eq!(try_crc_recovery(corrupted.clone(), 1), None);
+        assert_eq!(try_crc_recovery(corrupted, 2), Some((FRAME.to_vec(), 2)));
+    }
+
+    #[test]
+    fn test_crc_recovery_off() {
+        let mut corrupted = FRAME.to_vec();
+        corrupted[5] ^= 0x20;
+
+        assert_eq!(try_crc_recovery(corrupted, 0), None);
+    }
+
+    #[test]
+    fn test_crc_recovery_short() {
+        let mut frame = vec![0x5D, 0x48, 0x40, 0xD6];
+        let crc = get_adsb_crc(&frame);
+        frame.extend_from_slice(&[(crc >> 16) as u8, (crc >> 8) as u8, crc as u8]);
+
+        let mut corrupted = frame.clone();
+        corrupted[1] ^= 0x04;
+
+        assert_eq!(try_crc_recovery(corrupted, 1), Some((frame, 1)));
+    }
+}
@@ -2,19 +2,232 @@
 /// 
 /// Author: Jack Duignan (JackpDuignan@gmail.com)
 
-use chrono::Local;
+use chrono::{DateTime, Local};
+use num_complex::Complex;
 use plotters::prelude::*;
 
-use crate::adsb::crc::{get_adsb_crc, try_crc_recovery};
+use crate::adsb::crc::{get_syndrome, try_crc_recovery};
+use crate::cli::ErrorCorrection;
 
 const HIGH_THRESHOLD_DERATE: f64 = 0.9;
 
-/// Check that a packet is a vaild adsb frame and is worth decoding
+/// Number of samples in the preamble
+pub const PREAMBLE_SAMPLES: usize = 16;
+/// Number of bits in a short (56 bit) Mode S frame
+pub const SHORT_FRAME_BITS: usize = 56;
+/// Number of bits in a long (112 bit) Mode S frame
+pub const LONG_FRAME_BITS: usize = 112;
+
+/// Power of a full scale signal, the largest magnitude of a 16 bit complex sample
+const FULL_SCALE_POWER: f64 = 32768.0 * 32768.0;
+
+/// Minimum ratio of the preamble pulses to the gaps between them for a strong signal
+pub const STRONG_SIGNAL_RATIO: u32 = 4;
+
+/// Frequency of the clock used for frame timestamps in Hz
+pub const TIMESTAMP_CLOCK_HZ: f64 = 12_000_000.0;
+
+/// Downlink formats that are worth demodulating
+pub const SUPPORTED_DOWNLINK_FORMATS: [u8; 9] = [0, 4, 5, 11, 16, 17, 18, 20, 21];
+
+/// A frame found in a magnitude buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct DemodFrame {
+    pub packet: Vec<u8>,
+    /// Number of bits fixed by error correction
+    pub corrected_bits: usize,
+    /// Signal power of the frame in dBFS
+    pub signal_power: f64,
+    /// Noise floor before the frame in dBFS
+    pub noise_power: f64,
+    /// Number of samples from the start of the preamble to the end of the frame
+    pub length: usize,
+}
+
+/// Finds and demodulates Mode S frames in a magnitude buffer
+pub trait Demodulator {
+    /// Returns the number of samples needed from the start of a preamble to hold a long frame
+    fn get_window(&self) -> usize;
+
+    /// Try to demodulate a frame with the preamble starting at the given sample
+    ///
+    /// `mags` - the magnitude buffer, at least `get_window` samples must follow `start`
+    /// `start` - the sample to check for a preamble
+    /// `correction` - the error correction policy
+    /// `stats` - the statistics to update
+    ///
+    /// returns the frame if it passes the parity checks
+    fn demodulate(&self, mags: &[u32], start: usize, correction: ErrorCorrection, stats: &mut DemodStats) -> Option<DemodFrame>;
+}
+
+/// Demodulator for exactly 2 samples per microsecond
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TwoSampleDemod;
+
+impl Demodulator for TwoSampleDemod {
+    fn get_window(&self) -> usize {
+        PREAMBLE_SAMPLES + LONG_FRAME_BITS * 2
+    }
+
+    fn demodulate(&self, mags: &[u32], start: usize, correction: ErrorCorrection, stats: &mut DemodStats) -> Option<DemodFrame> {
+        let check_mags: &[u32; 32] = mags[start..start + 32]
+                        .try_into()
+                        .expect("Bad packet length passed to adsb checker");
+
+        let (high, noise_power) = check_for_adsb_packet(check_mags)?;
+        stats.preambles += 1;
+
+        let frame_start = start + PREAMBLE_SAMPLES;
+        let frame_samples = get_frame_bits(get_downlink_format(&mags[frame_start..])) * 2;
+        let strong_signal = is_strong_signal(&check_mags[0..PREAMBLE_SAMPLES]);
+        let frame = &mags[frame_start..frame_start + frame_samples];
+
+        let (packet, corrected_bits) = extract_packet(frame.to_vec(), high, correction, strong_signal)?;
+
+        Some(DemodFrame {
+            packet: packet,
+            corrected_bits: corrected_bits,
+            signal_power: get_signal_power(frame),
+            noise_power: noise_power,
+            length: PREAMBLE_SAMPLES + frame_samples,
+        })
+    }
+}
+
+/// What the consumer of the frames did with a frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameAction {
+    /// The frame is used and its samples skipped
+    Use,
+    /// The frame is dropped and its samples are still searched
+    Reject,
+    /// The consumer has gone so demodulation stops
+    Stop,
+}
+
+/// Runs a demodulator over a stream of sample buffers
+///
+/// The samples at the end of each buffer that are too close to the end to hold a frame
+/// are kept and searched at the start of the next buffer.
+pub struct StreamDemod {
+    demodulator: Box<dyn Demodulator + Send>,
+    /// Samples from the previous buffer that haven't been searched
+    tail: Vec<u32>,
+    /// Index of the sample following the last buffer processed
+    next_sample: u64,
+}
+
+impl StreamDemod {
+    pub fn new(demodulator: Box<dyn Demodulator + Send>) -> Self {
+        StreamDemod {
+            demodulator: demodulator,
+            tail: Vec::new(),
+            next_sample: 0,
+        }
+    }
+
+    /// Search the next buffer of the stream for frames
+    ///
+    /// `first_sample` - the index of the first sample of the buffer in the stream, the kept
+    /// samples are dropped if the buffer doesn't follow on from the last one
+    /// `mags` - the magnitude buffer
+    /// `correction` - the error correction policy
+    /// `stats` - the statistics to update, frames are counted as good once used
+    /// `accept` - called with the index of the first sample and each frame, returns what was
+    /// done with the frame
+    ///
+    /// returns false if `accept` stopped the search
+    pub fn process<F>(&mut self, first_sample: u64, mags: &[u32], correction: ErrorCorrection, stats: &mut DemodStats, mut accept: F) -> bool
+    where
+        F: FnMut(u64, DemodFrame) -> FrameAction,
+    {
+        let mut buf = std::mem::take(&mut self.tail);
+        if first_sample != self.next_sample {
+            buf.clear();
+        }
+        buf.extend_from_slice(mags);
+
+        let buf_start = first_sample - (buf.len() - mags.len()) as u64;
+        self.next_sample = first_sample + mags.len() as u64;
+
+        let window = self.demodulator.get_window();
+        let mut i = 0;
+        while i + window <= buf.len() {
+            if let Some(frame) = self.demodulator.demodulate(&buf, i, correction, stats) {
+                let (length, corrected_bits) = (frame.length, frame.corrected_bits);
+                match accept(buf_start + i as u64, frame) {
+                    FrameAction::Use => {
+                        stats.add_good(corrected_bits);
+                        i += length;
+                        continue;
+                    },
+                    FrameAction::Reject => (),
+                    FrameAction::Stop => return false,
+                }
+            }
+            i += 1;
+        }
+
+        self.tail = buf.split_off(i.min(buf.len()));
+        true
+    }
+}
+
+/// A buffer of samples read from the sdr or a recording
+#[derive(Debug, Clone)]
+pub struct SampleBuffer<T = Complex<i16>> {
+    /// Index of the first sample since the stream started
+    pub first_sample: u64,
+    pub samples: Vec<T>,
+}
+
+/// Converts the index of a sample in the stream into timestamps
+#[derive(Debug, Clone, Copy)]
+pub struct SampleClock {
+    /// Wall clock time of the first sample
+    start: DateTime<Local>,
+    /// Sample rate in Hz
+    sample_rate: f64,
+}
+
+impl SampleClock {
+    pub fn new(start: DateTime<Local>, sample_rate: f64) -> Self {
+        SampleClock {
+            start: start,
+            sample_rate: sample_rate,
+        }
+    }
+
+    /// Get the timestamp of a sample in ticks of a 12 MHz clock as used for multilateration
+    ///
+    /// `sample` - the index of the sample in the stream
+    pub fn get_ticks(&self, sample: u64) -> u64 {
+        (sample as f64 * TIMESTAMP_CLOCK_HZ / self.sample_rate).round() as u64
+    }
+
+    /// Get the wall clock time of a sample
+    ///
+    /// `sample` - the index of the sample in the stream
+    pub fn get_time(&self, sample: u64) -> DateTime<Local> {
+        self.start + chrono::Duration::nanoseconds((sample as f64 * 1e9 / self.sample_rate).round() as i64)
+    }
+
+    /// Get the index of the sample taken at a wall clock time
+    ///
+    /// `time` - the wall clock time, times before the first sample give the first sample
+    pub fn get_sample(&self, time: DateTime<Local>) -> u64 {
+        let elapsed = (time - self.start).num_nanoseconds().unwrap_or(i64::MAX).max(0);
+        (elapsed as f64 * self.sample_rate / 1e9).round() as u64
+    }
+}
+
+/// Check that a packet is a vaild Mode S frame and is worth decoding
 /// 
-/// buf - the buffer to check size: 16+16=32 (preamble and first byte)
+/// buf - the buffer to check size: 16+16=32 (preamble and first byte), borrowed
+/// straight from the magnitude buffer so the sliding window isn't copied
 /// 
-/// returns high value, signal power, noise power
-pub fn check_for_adsb_packet(buf: [u32; 32]) -> Option<(u32, i32, i32)> {
+/// returns high value and the noise floor in dBFS
+pub fn check_for_adsb_packet(buf: &[u32; 32]) -> Option<(u32, f64)> {
     // Adsb pre amble has the following form so check it:
     //
     // +   -   +   -   -   -   -   +   -   +   -   -   -   -   -   -
@@ -22,63 +235,184 @@ pub fn check_for_adsb_packet(buf: [u32; 32]) -> Option<(u32, i32, i32)> {
     // 0   1   2   3   4   5   6   7   8   9  10  11  12  13  14  15 
     let lows = [1, 3, 4, 5, 6, 8, 10, 11, 12, 13, 14, 15];
     let highs = [0, 2, 7, 9];
-    let mut min = u32::MAX;
 
-    for high in highs.iter() {
-        for low in lows.iter() {
-            if buf[*high as usize] < buf[*low as usize] {
-                return None;
-            }
-        }
-        if buf[*high as usize] < min {
-            min = buf[*high as usize];
+    // Most windows are noise so reject on the first pulse pair before the full check
+    if buf[0] < buf[1] || buf[2] < buf[3] {
+        return None;
+    }
+
+    // Every high has to be above every low
+    let min = highs.iter().map(|i| buf[*i]).min().unwrap_or(0);
+    if lows.iter().any(|i| buf[*i] > min) {
+        return None;
+    }
+
+    // The preamble is followed by the downlink format which sets the frame length
+    if !SUPPORTED_DOWNLINK_FORMATS.contains(&get_downlink_format(&buf[PREAMBLE_SAMPLES..])) {
+        return None;
+    }
+
+    Some(((min as f32 * 0.9) as u32, get_noise_power(&buf[0..PREAMBLE_SAMPLES])))
+}
+
+/// Convert a mean squared magnitude into dBFS
+///
+/// `power` - the mean squared magnitude
+pub fn to_dbfs(power: f64) -> f64 {
+    // Guard against log of zero for silent buffers
+    10.0 * (power.max(1.0) / FULL_SCALE_POWER).log10()
+}
+
+/// Estimate the noise floor from the gaps in the preamble
+///
+/// Samples next to the pulses are skipped as they hold the pulse edges.
+///
+/// `buf` - the preamble samples
+///
+/// returns the noise power in dBFS
+pub fn get_noise_power(buf: &[u32]) -> f64 {
+    let gaps = [4, 5, 6, 11, 12, 13, 14, 15];
+    let power = gaps.iter().map(|i| (buf[*i] as f64).powi(2)).sum::<f64>() / gaps.len() as f64;
+
+    to_dbfs(power)
+}
+
+/// Measure the signal power of a frame from the pulse of each bit
+///
+/// `buf` - the frame samples (two per bit)
+///
+/// returns the signal power in dBFS
+pub fn get_signal_power(buf: &[u32]) -> f64 {
+    let bits = buf.len() / 2;
+    let power = buf.chunks_exact(2)
+        .map(|bit| (bit[0].max(bit[1]) as f64).powi(2))
+        .sum::<f64>() / bits.max(1) as f64;
+
+    to_dbfs(power)
+}
+
+/// Check if the preamble is strong enough to trust two bit error correction
+///
+/// `buf` - the preamble samples
+///
+/// returns true if every pulse is well above every gap
+pub fn is_strong_signal(buf: &[u32]) -> bool {
+    let highs = [0, 2, 7, 9];
+    let lows = [1, 3, 4, 5, 6, 8, 10, 11, 12, 13, 14, 15];
+
+    let min_high = highs.iter().map(|i| buf[*i]).min().unwrap_or(0);
+    let max_low = lows.iter().map(|i| buf[*i]).max().unwrap_or(0);
+
+    min_high >= max_low.saturating_mul(STRONG_SIGNAL_RATIO)
+}
+
+/// Counts of frames handled by the demodulator
+#[derive(Debug, Clone, Default)]
+pub struct DemodStats {
+    /// Preambles that passed the preamble check
+    pub preambles: u32,
+    /// Frames that passed the crc check after any correction
+    pub good: u32,
+    /// Frames with a single bit corrected
+    pub corrected_one_bit: u32,
+    /// Frames with two bits corrected
+    pub corrected_two_bit: u32,
+}
+
+impl DemodStats {
+    /// Record the number of bits corrected in a good frame
+    pub fn add_good(&mut self, corrected_bits: usize) {
+        self.good += 1;
+        match corrected_bits {
+            1 => self.corrected_one_bit += 1,
+            2 => self.corrected_two_bit += 1,
+            _ => (),
         }
     }
+}
 
-    // The preamble is followed by DF which needs to be 17 for adsb
-    //
-    // This translates too:
-    //   1       0       0       0       1
-    // +   -   -   +   -   +   -   +   +   -
-    // 0  0.5  1  1.5  2  2.5  3  3.5  4  4.5
-    // 0   1   2   3   4   5   6   7   8   9 
-    let lows = [1, 2, 4, 6, 9];
-    let highs = [0, 3, 5, 7, 8];
-
-    for high in highs.iter() {
-        for low in lows.iter() {
-            if buf[*high + 16 as usize] < buf[*low + 16 as usize] {
-                return None;
-            }
+impl std::fmt::Display for DemodStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Preambles           : {}", self.preambles)?;
+        writeln!(f, "Good Frames         : {}", self.good)?;
+        writeln!(f, "Corrected 1 Bit     : {}", self.corrected_one_bit)?;
+        writeln!(f, "Corrected 2 Bit     : {}", self.corrected_two_bit)?;
+
+        Ok(())
+    }
+}
+
+/// Read the downlink format from the first 5 bits following the preamble
+/// 
+/// `buf` - the magnitude buffer starting at the first data sample (at least 10 samples)
+/// 
+/// returns the downlink format
+pub fn get_downlink_format(buf: &[u32]) -> u8 {
+    let mut downlink_format = 0;
+
+    for bit in 0..5 {
+        downlink_format <<= 1;
+        if buf[bit * 2] > buf[bit * 2 + 1] {
+            downlink_format |= 1;
         }
     }
 
-    Some(((min as f32 * 0.9) as u32, 0, 0))
+    downlink_format
+}
+
+/// Get the number of bits in a frame of the given downlink format
+/// 
+/// `downlink_format` - the downlink format of the frame
+/// 
+/// returns 112 for long formats (DF16 and up) and 56 otherwise
+pub fn get_frame_bits(downlink_format: u8) -> usize {
+    if downlink_format >= 16 {
+        LONG_FRAME_BITS
+    } else {
+        SHORT_FRAME_BITS
+    }
 }
 
 /// Extract a packet from a buffer of magnitude values
 /// 
-/// `buf` - the buffer to extract
+/// `buf` - the buffer to extract (56 or 112 bits after the preamble)
 /// `high` - the high level to use
+/// `correction` - the error correction policy
+/// `strong_signal` - true if the signal is strong enough for two bit correction
 /// 
-/// returns byte vector if packet is correct and worth looking at
-pub fn extract_packet(buf: Vec<u32>, high: u32) -> Option<Vec<u8>> {
+/// returns byte vector and the number of corrected bits if packet is correct and worth looking at
+pub fn extract_packet(buf: Vec<u32>, high: u32, correction: ErrorCorrection, strong_signal: bool) -> Option<(Vec<u8>, usize)> {
     let extracted_manchester = extract_manchester_relative(buf.to_vec(), (high as f64 * HIGH_THRESHOLD_DERATE) as u32)?;
 
     let packet = decode_packet(extracted_manchester.clone())?;
 
-    let len = packet.len();
-    let calced_crc = get_adsb_crc(&packet[0..len-3].to_vec());
-    let packet_crc = ((packet[len-1] as u32) << 0) | 
-                            ((packet[len-2] as u32) << 8) |
-                            ((packet[len-3] as u32) << 16);
-    
-    if calced_crc != packet_crc {
-        // println!("Trying to recover packet with crc: {:06X}, calculated: {:06X}", packet_crc, calced_crc);
-        return try_crc_recovery(packet, calced_crc, packet_crc);
-    }
+    check_packet(packet, correction, strong_signal)
+}
 
-    Some(packet)
+/// Check the parity of a packet correcting it if allowed
+///
+/// `packet` - the demodulated packet
+/// `correction` - the error correction policy
+/// `strong_signal` - true if the signal is strong enough for two bit correction
+///
+/// returns the packet and the number of corrected bits if it is correct and worth looking at
+pub fn check_packet(packet: Vec<u8>, correction: ErrorCorrection, strong_signal: bool) -> Option<(Vec<u8>, usize)> {
+    let syndrome = get_syndrome(&packet);
+
+    match packet[0] >> 3 {
+        17 if syndrome != 0 => {
+            // Two bit corrections are only trusted with a full length DF17 and a clean signal
+            let max_errors = if strong_signal { correction.max_errors() } else { correction.max_errors().min(1) };
+            try_crc_recovery(packet, max_errors)
+        },
+        18 if syndrome != 0 => try_crc_recovery(packet, correction.max_errors().min(1)),
+        17 | 18 => Some((packet, 0)),
+        // All call replies have the interrogator code overlaid on the low 7 bits of the parity
+        11 if syndrome < 0x80 => Some((packet, 0)),
+        // Address/parity replies are validated against the known aircraft once decoded
+        0 | 4 | 5 | 16 | 20 | 21 => Some((packet, 0)),
+        _ => None,
+    }
 }
 
 /// Extract the manchester values of a packet
@@ -246,6 +580,8 @@ pub fn plot_adsb_packet(mag_buf: Vec<u32>, _manchester: Vec<u16>, _decoded: Vec<
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::adsb::crc::get_adsb_crc;
+    use crate::adsb::fixtures::{to_samples, FRAME, PREAMBLE};
 
     #[test]
     fn test_check_for_adsb_packet_valid() {
@@ -258,7 +594,7 @@ mod tests {
             buf[i] = 500; // lows
         }
 
-        let result = check_for_adsb_packet(buf);
+        let result = check_for_adsb_packet(&buf);
         assert!(result.is_some());
         assert_eq!(result.unwrap().0, (1000.0 * 0.9) as u32);
     }
@@ -274,7 +610,165 @@ mod tests {
             buf[i] = 1000; // lows
         }
 
-        assert_eq!(check_for_adsb_packet(buf), None);
+        assert_eq!(check_for_adsb_packet(&buf), None);
+    }
+
+    #[test]
+    fn test_check_for_adsb_packet_unsupported_df() {
+        let mut buf = [0u32; 32];
+        for &i in &[0, 2, 7, 9] {
+            buf[i] = 1000; // highs
+        }
+        for &i in &[1, 3, 4, 5, 6, 8, 10, 11, 12, 13, 14, 15] {
+            buf[i] = 500; // lows
+        }
+        // DF31 is not a supported format
+        for i in (16..26).step_by(2) {
+            buf[i] = 1000;
+        }
+
+        assert_eq!(check_for_adsb_packet(&buf), None);
+    }
+
+    #[test]
+    fn test_get_frame_bits() {
+        assert_eq!(get_frame_bits(0), 56);
+        assert_eq!(get_frame_bits(11), 56);
+        assert_eq!(get_frame_bits(17), 112);
+        assert_eq!(get_frame_bits(21), 112);
+    }
+
+    #[test]
+    fn test_extract_packet_short() {
+        // DF11 all call reply with a zero interrogator code
+        let mut packet = vec![0x5D, 0x48, 0x40, 0xD6];
+        let crc = get_adsb_crc(&packet);
+        packet.extend_from_slice(&[(crc >> 16) as u8, (crc >> 8) as u8, crc as u8]);
+
+        let buf = to_samples(&packet);
+
+        assert_eq!(get_downlink_format(&buf), 11);
+        assert_eq!(buf.len(), get_frame_bits(11) * 2);
+        assert_eq!(extract_packet(buf, 100, ErrorCorrection::OneBit, false), Some((packet, 0)));
+    }
+
+    #[test]
+    fn test_extract_packet_correction() {
+        let packet = FRAME.to_vec();
+        let mut one_bit = packet.clone();
+        one_bit[6] ^= 0x04;
+        let mut two_bit = one_bit.clone();
+        two_bit[10] ^= 0x80;
+
+        assert_eq!(extract_packet(to_samples(&one_bit), 100, ErrorCorrection::Off, true), None);
+        assert_eq!(extract_packet(to_samples(&one_bit), 100, ErrorCorrection::OneBit, false), Some((packet.clone(), 1)));
+        assert_eq!(extract_packet(to_samples(&two_bit), 100, ErrorCorrection::OneBit, true), None);
+        assert_eq!(extract_packet(to_samples(&two_bit), 100, ErrorCorrection::TwoBit, false), None);
+        assert_eq!(extract_packet(to_samples(&two_bit), 100, ErrorCorrection::TwoBit, true), Some((packet, 2)));
+    }
+
+    /// Run a stream of buffers through the demodulator returning the extended squitters found
+    fn demod_stream(stream: &mut StreamDemod, first_sample: u64, buffers: &[&[u32]]) -> Vec<(u64, Vec<u8>)> {
+        let mut stats = DemodStats::default();
+        let mut packets = Vec::new();
+        let mut first_sample = first_sample;
+
+        for buf in buffers {
+            stream.process(first_sample, buf, ErrorCorrection::Off, &mut stats, |sample, frame| {
+                if frame.packet[0] >> 3 != 17 {
+                    return FrameAction::Reject;
+                }
+                packets.push((sample, frame.packet));
+                FrameAction::Use
+            });
+            first_sample += buf.len() as u64;
+        }
+
+        packets
+    }
+
+    #[test]
+    fn test_stream_split_frame() {
+        let packet = FRAME.to_vec();
+        let mut mags = vec![0u32; 300];
+        mags.extend_from_slice(&PREAMBLE);
+        mags.extend(to_samples(&packet));
+        mags.extend(vec![0u32; 300]);
+
+        // Split the stream part way through the frame
+        let (first, second) = mags.split_at(400);
+
+        let mut stream = StreamDemod::new(Box::new(TwoSampleDemod));
+        assert_eq!(demod_stream(&mut stream, 0, &[first, second]), vec![(300, packet.clone())]);
+
+        // Each half on its own doesn't hold the whole frame
+        assert!(demod_stream(&mut StreamDemod::new(Box::new(TwoSampleDemod)), 0, &[first]).is_empty());
+        assert!(demod_stream(&mut StreamDemod::new(Box::new(TwoSampleDemod)), 400, &[second]).is_empty());
+
+        // Many small buffers are joined as well
+        let mut stream = StreamDemod::new(Box::new(TwoSampleDemod));
+        let chunks: Vec<&[u32]> = mags.chunks(37).collect();
+        assert_eq!(demod_stream(&mut stream, 1000, &chunks), vec![(1300, packet)]);
+    }
+
+    #[test]
+    fn test_stream_gap() {
+        let packet = FRAME.to_vec();
+        let mut mags = vec![0u32; 300];
+        mags.extend_from_slice(&PREAMBLE);
+        mags.extend(to_samples(&packet));
+        let (first, second) = mags.split_at(400);
+
+        // Samples lost between the buffers mean the halves can't be joined
+        let mut stream = StreamDemod::new(Box::new(TwoSampleDemod));
+        assert!(demod_stream(&mut stream, 0, &[first]).is_empty());
+        assert!(demod_stream(&mut stream, 500, &[second]).is_empty());
+    }
+
+    #[test]
+    fn test_sample_clock() {
+        let start = Local::now();
+        let clock = SampleClock::new(start, 2_400_000.0);
+
+        assert_eq!(clock.get_ticks(0), 0);
+        assert_eq!(clock.get_ticks(1), 5);
+        assert_eq!(clock.get_ticks(2_400_000 * 3600), 12_000_000 * 3600);
+        assert_eq!(clock.get_time(1_200_000), start + chrono::Duration::milliseconds(500));
+
+        assert_eq!(clock.get_sample(start + chrono::Duration::milliseconds(500)), 1_200_000);
+        assert_eq!(clock.get_sample(clock.get_time(123_456_789)), 123_456_789);
+        assert_eq!(clock.get_sample(start - chrono::Duration::seconds(1)), 0);
+    }
+
+    #[test]
+    fn test_signal_power() {
+        let full_scale = vec![32768, 0, 0, 32768];
+        assert!(get_signal_power(&full_scale).abs() < 1e-9);
+
+        let half_scale = vec![16384, 10, 10, 16384];
+        assert!((get_signal_power(&half_scale) - -6.02).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_noise_power() {
+        let mut buf = [3277u32; 16];
+        for &i in &[0, 2, 7, 9] {
+            buf[i] = 32768;
+        }
+
+        assert!((get_noise_power(&buf) - -20.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_is_strong_signal() {
+        let mut buf = [10u32; 16];
+        for &i in &[0, 2, 7, 9] {
+            buf[i] = 100;
+        }
+        assert!(is_strong_signal(&buf));
+
+        buf[5] = 40;
+        assert!(!is_strong_signal(&buf));
     }
 
     #[test]
@@ -376,6 +870,6 @@ mod tests {
         }
 
         // This will decode to a valid pattern but incorrect CRC
-        assert!(extract_packet(buf, high).is_none());
+        assert!(extract_packet(buf, high, ErrorCorrection::TwoBit, true).is_none());
     }
 }
\ No newline at end of file
@@ -0,0 +1,61 @@
+/// Frames and reference implementations shared by the tests and benchmarks
+///
+/// Author: Jack Duignan (JackpDuignan@gmail.com)
+
+/// DF17 extended squitter with a valid parity
+pub const FRAME: [u8; 14] = [0x8D, 0x40, 0x6B, 0x90, 0x20, 0x15, 0xA6, 0x78, 0xD4, 0xD2, 0x20, 0xAA, 0x4B, 0xDA];
+
+/// Preamble magnitude samples at 2 samples per microsecond
+pub const PREAMBLE: [u32; 16] = [120, 50, 120, 50, 50, 50, 50, 120, 50, 120, 50, 50, 50, 50, 50, 50];
+
+/// Reference crc which divides one bit at a time
+///
+/// `buf` - the bytes to find the remainder of
+pub fn get_adsb_crc_bitwise(buf: &[u8]) -> u32 {
+    const GENERATOR: u32 = 0b1_1111_1111_1111_0100_0000_1001;
+    const GENERATOR_LEN: usize = 24;
+
+    let mut bits = Vec::with_capacity(buf.len() * 8 + GENERATOR_LEN);
+    for byte in buf {
+        for i in (0..8).rev() {
+            bits.push((byte >> i) & 1 != 0);
+        }
+    }
+
+    bits.extend(std::iter::repeat_n(false, GENERATOR_LEN));
+
+    for i in 0..(bits.len() - GENERATOR_LEN) {
+        if bits[i] {
+            for j in 0..=GENERATOR_LEN {
+                bits[i + j] ^= ((GENERATOR >> (GENERATOR_LEN - j)) & 1) != 0;
+            }
+        }
+    }
+
+    let mut remainder = 0u32;
+    for i in 0..GENERATOR_LEN {
+        if bits[bits.len() - GENERATOR_LEN + i] {
+            remainder |= 1 << (GENERATOR_LEN - 1 - i);
+        }
+    }
+
+    remainder
+}
+
+/// Convert a packet into magnitude samples at 2 samples per bit
+///
+/// `packet` - the frame to send
+pub fn to_samples(packet: &[u8]) -> Vec<u32> {
+    let mut buf = Vec::new();
+    for byte in packet.iter() {
+        for i in (0..8).rev() {
+            if (byte >> i) & 1 == 1 {
+                buf.extend_from_slice(&[120, 50]);
+            } else {
+                buf.extend_from_slice(&[50, 120]);
+            }
+        }
+    }
+
+    buf
+}
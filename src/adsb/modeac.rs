@@ -0,0 +1,143 @@
+/// Decoding of Mode A/C style codes carried in Mode S replies
+///
+/// Author: Jack Duignan (JackpDuignan@gmail.com)
+
+/// Convert a gray coded value to binary
+fn gray_to_binary(gray: u32) -> u32 {
+    let mut binary = gray;
+    let mut shift = gray >> 1;
+
+    while shift != 0 {
+        binary ^= shift;
+        shift >>= 1;
+    }
+
+    binary
+}
+
+/// Decode a Gillham (gray) coded altitude
+///
+/// `ac13` - the 13 bit altitude code: C1 A1 C2 A2 C4 A4 M B1 Q B2 D2 B4 D4
+///
+/// returns the altitude in feet or None if the code is invalid
+fn gillham_to_altitude(ac13: u16) -> Option<i32> {
+    let bit = |n: u16| ((ac13 >> n) & 1) as u32;
+
+    // D1 is never used for altitude so the 500 ft code is D2 D4 A1 A2 A4 B1 B2 B4
+    let gray_500 = [bit(2), bit(0), bit(11), bit(9), bit(7), bit(5), bit(3), bit(1)]
+        .iter()
+        .fold(0, |acc, b| (acc << 1) | b);
+    let gray_100 = (bit(12) << 2) | (bit(10) << 1) | bit(8);
+
+    let five_hundreds = gray_to_binary(gray_500) as i32;
+    let mut one_hundreds = match gray_to_binary(gray_100) {
+        0 | 5 | 6 => return None,
+        7 => 5,
+        n => n as i32,
+    };
+
+    // The 100 ft code counts down on odd 500 ft increments
+    if five_hundreds % 2 == 1 {
+        one_hundreds = 6 - one_hundreds;
+    }
+
+    Some(five_hundreds * 500 + one_hundreds * 100 - 1300)
+}
+
+/// Decode the 13 bit altitude code field of a surveillance reply (DF0, DF4, DF16 and DF20)
+///
+/// `ac13` - the altitude code field
+///
+/// returns the altitude in feet or None if it is unknown
+pub fn decode_ac13(ac13: u16) -> Option<i32> {
+    const M_BIT: u16 = 1 << 6;
+    const Q_BIT: u16 = 1 << 4;
+
+    if ac13 == 0 {
+        return None;
+    }
+
+    if ac13 & M_BIT != 0 {
+        // Metric altitude is the remaining 12 bits in metres
+        let metres = ((ac13 & 0x1F80) >> 1) | (ac13 & 0x3F);
+        return Some((metres as f64 * 3.28084).round() as i32);
+    }
+
+    if ac13 & Q_BIT != 0 {
+        // 25 ft increments with the M and Q bits removed
+        let n = ((ac13 & 0x1F80) >> 2) | ((ac13 & 0x20) >> 1) | (ac13 & 0xF);
+        return Some(n as i32 * 25 - 1000);
+    }
+
+    gillham_to_altitude(ac13)
+}
+
+/// Decode the 12 bit altitude field of an airborne position message
+///
+/// `ac12` - the altitude field, the 13 bit code with the M bit removed
+///
+/// returns the altitude in feet or None if it is unknown
+pub fn decode_ac12(ac12: u16) -> Option<i32> {
+    decode_ac13(((ac12 & 0xFC0) << 1) | (ac12 & 0x3F))
+}
+
+/// Decode the 13 bit identity field of a surveillance reply (DF5 and DF21) or status message
+///
+/// `id13` - the identity code: C1 A1 C2 A2 C4 A4 X B1 D1 B2 D2 B4 D4
+///
+/// returns the Mode A squawk with each octal digit in 3 bits (7700 is 0o7700)
+pub fn decode_id13(id13: u16) -> u16 {
+    let bit = |n: u16| (id13 >> n) & 1;
+
+    let a = (bit(7) << 2) | (bit(9) << 1) | bit(11);
+    let b = (bit(1) << 2) | (bit(3) << 1) | bit(5);
+    let c = (bit(8) << 2) | (bit(10) << 1) | bit(12);
+    let d = (bit(0) << 2) | (bit(2) << 1) | bit(4);
+
+    (a << 9) | (b << 6) | (c << 3) | d
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gray_to_binary() {
+        assert_eq!(gray_to_binary(0b000), 0);
+        assert_eq!(gray_to_binary(0b110), 4);
+        assert_eq!(gray_to_binary(0b100), 7);
+    }
+
+    #[test]
+    fn test_decode_ac12_25ft() {
+        assert_eq!(decode_ac12(0xC38), Some(38000));
+        assert_eq!(decode_ac12(0x011), Some(-975));
+    }
+
+    #[test]
+    fn test_decode_ac12_gillham() {
+        assert_eq!(decode_ac12(0x200), Some(-1000));
+        assert_eq!(decode_ac12(0xC28), Some(28300));
+    }
+
+    #[test]
+    fn test_decode_ac12_unknown() {
+        assert_eq!(decode_ac12(0x000), None);
+        // 100 ft code of zero is invalid
+        assert_eq!(decode_ac12(0x020), None);
+    }
+
+    #[test]
+    fn test_decode_ac13() {
+        assert_eq!(decode_ac13(0x14B4), Some(32300));
+        assert_eq!(decode_ac13(0x1838), Some(38000));
+        assert_eq!(decode_ac13(0x0400), Some(-1000));
+    }
+
+    #[test]
+    fn test_decode_id13() {
+        assert_eq!(decode_id13(0x0AAA), 0o7700);
+        assert_eq!(decode_id13(0x0808), 0o1200);
+        assert_eq!(decode_id13(0x0000), 0o0000);
+    }
+}
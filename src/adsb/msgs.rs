@@ -2,11 +2,24 @@
 ///
 /// Jack Duignan (JackpDuignan@gmail.com)
 
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::adsb::modeac::{decode_ac12, decode_id13};
+use crate::adsb::packet::{AllCallReply, SurveillanceReply};
+
 /// Top level enum to hold various message types
 #[derive(Debug, Clone)]
 pub enum AdsbMsgType {
     AircraftID(AircraftID),
     AircraftPosition(AircraftPosition),
+    AirborneVelocity(AirborneVelocity),
+    SurfacePosition(SurfacePosition),
+    OperationalStatus(OperationalStatus),
+    AircraftStatus(AircraftStatus),
+    TargetStateStatus(TargetStateStatus),
+    AllCallReply(AllCallReply),
+    SurveillanceReply(SurveillanceReply),
     Uknown(UknownMsg)
 }
 
@@ -17,6 +30,20 @@ impl std::fmt::Display for AdsbMsgType {
                 write!(f, "{}", id),
             AdsbMsgType::AircraftPosition(pos) =>
                 write!(f, "{}", pos),
+            AdsbMsgType::AirborneVelocity(vel) =>
+                write!(f, "{}", vel),
+            AdsbMsgType::SurfacePosition(pos) =>
+                write!(f, "{}", pos),
+            AdsbMsgType::OperationalStatus(status) =>
+                write!(f, "{}", status),
+            AdsbMsgType::AircraftStatus(status) =>
+                write!(f, "{}", status),
+            AdsbMsgType::TargetStateStatus(state) =>
+                write!(f, "{}", state),
+            AdsbMsgType::AllCallReply(reply) =>
+                write!(f, "{}", reply),
+            AdsbMsgType::SurveillanceReply(reply) =>
+                write!(f, "{}", reply),
             AdsbMsgType::Uknown(msg) =>
                 write!(f, "{}", msg),
         }
@@ -43,6 +70,23 @@ impl std::fmt::Display for UknownMsg {
     }
 }
 
+/// Extract a field from a message
+///
+/// `msg` - the message bytes
+/// `start` - the first bit of the field (1 indexed as in the specification)
+/// `len` - the number of bits in the field
+///
+/// returns the field value
+pub(crate) fn get_bits(msg: &[u8], start: usize, len: usize) -> u32 {
+    let mut value = 0u32;
+
+    for bit in (start - 1)..(start - 1 + len) {
+        value = (value << 1) | ((msg[bit / 8] >> (7 - bit % 8)) & 1) as u32;
+    }
+
+    value
+}
+
 /// CPR message parity (even or odd)
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CprFormat {
@@ -50,6 +94,13 @@ pub enum CprFormat {
     Odd,
 }
 
+/// Source of the altitude in a position message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AltitudeSource {
+    Barometric,
+    Gnss,
+}
+
 /// Aircraft position message
 #[derive(Debug, Clone)]
 pub struct AircraftPosition {
@@ -59,7 +110,8 @@ pub struct AircraftPosition {
     surveillance_status: u8,
     nic_supplement: u8,
     /// The altitude in feet
-    pub altitude: i32,
+    pub altitude: Option<i32>,
+    pub altitude_source: AltitudeSource,
     pub cpr_time: u8,
     pub cpr_format: CprFormat,
     pub cpr_latitude: u32,
@@ -68,13 +120,17 @@ pub struct AircraftPosition {
 
 impl AircraftPosition {
     pub fn new(msg: [u8; 7]) -> Self {
-        let alt_mode_25 = msg[1] & (1 << 0) == 1;
-        let mut altitude = ((((msg[1] & (0b1111_1110)) >> 1) as i32) << 4) | (((msg[2] & 0xF0) as i32) >> 4);
-
-        altitude *= if alt_mode_25 { 25 } else { 100 };
-        altitude -= 1000;
-
         let msg_type = (msg[0] & 0b1111_1000) >> 3;
+
+        let altitude_source = if (20..=22).contains(&msg_type) { AltitudeSource::Gnss } else { AltitudeSource::Barometric };
+        let altitude = match altitude_source {
+            AltitudeSource::Barometric => decode_ac12(get_bits(&msg, 9, 12) as u16),
+            // GNSS height is reported in metres
+            AltitudeSource::Gnss => match get_bits(&msg, 9, 12) {
+                0 => None,
+                metres => Some((metres as f64 * 3.28084).round() as i32),
+            },
+        };
         let ss = (msg[0] & 0b0000_0110) >> 1;
         let nic = msg[0] & 0b0000_0001;
         let time = (msg[2] & 0b0000_1000) >> 3;
@@ -94,6 +150,7 @@ impl AircraftPosition {
             surveillance_status: ss,
             nic_supplement: nic,
             altitude,
+            altitude_source,
             cpr_time: time,
             cpr_format,
             cpr_latitude: latitude,
@@ -102,10 +159,41 @@ impl AircraftPosition {
     }
 
     /// Returns the altitude in feet
-    pub fn get_altitude_ft(&self) -> i32 {
+    pub fn get_altitude_ft(&self) -> Option<i32> {
         self.altitude
     }
 
+    /// Returns the source of the altitude
+    pub fn get_altitude_source(&self) -> AltitudeSource {
+        self.altitude_source
+    }
+
+    /// Returns the navigation integrity category and containment radius
+    ///
+    /// `nic_a` - NIC supplement A from the operational status message
+    ///
+    /// returns the NIC and the containment radius in metres (None if unknown)
+    pub fn get_nic(&self, nic_a: bool) -> (u8, Option<f64>) {
+        let nic_b = self.nic_supplement == 1;
+
+        match (self.msg_type, nic_a, nic_b) {
+            (9, _, _) | (20, _, _) => (11, Some(7.5)),
+            (10, _, _) | (21, _, _) => (10, Some(25.0)),
+            (11, true, true) => (9, Some(75.0)),
+            (11, _, _) => (8, Some(185.2)),
+            (12, _, _) => (7, Some(370.4)),
+            (13, false, true) => (6, Some(555.6)),
+            (13, true, true) => (6, Some(1111.2)),
+            (13, _, _) => (6, Some(926.0)),
+            (14, _, _) => (5, Some(1852.0)),
+            (15, _, _) => (4, Some(3704.0)),
+            (16, true, true) => (3, Some(7408.0)),
+            (16, _, _) => (2, Some(14816.0)),
+            (17, _, _) => (1, Some(37040.0)),
+            _ => (0, None),
+        }
+    }
+
     /// Returns the cpr format
     pub fn get_cpr_format(&self) -> CprFormat {
         self.cpr_format
@@ -120,7 +208,7 @@ impl AircraftPosition {
 // Trait Implementations
 impl AdsbMsg for AircraftPosition {
     fn msg_id_match(id: u8) -> bool {
-        (9..=18).contains(&id)
+        (9..=18).contains(&id) || (20..=22).contains(&id)
     }
 }
 
@@ -130,7 +218,123 @@ impl std::fmt::Display for AircraftPosition {
         writeln!(f, "Type                : {} (Position)", self.msg_type)?;
         writeln!(f, "Surveillance Status : {}", self.surveillance_status)?;
         writeln!(f, "NIC Supplement      : {}", self.nic_supplement)?;
-        writeln!(f, "Altitude (ft)       : {}", self.altitude)?;
+        writeln!(f, "Altitude (ft)       : {}", display_option(self.altitude))?;
+        writeln!(f, "Altitude Source     : {:?}", self.altitude_source)?;
+        writeln!(f, "CPR Time            : {}", self.cpr_time)?;
+        writeln!(f, "CPR Format          : {:?}", self.cpr_format)?;
+        writeln!(f, "Raw Latitude        : {}", self.cpr_latitude)?;
+        writeln!(f, "Raw Longitude       : {}", self.cpr_longitude)?;
+        Ok(())
+    }
+}
+
+/// Surface position message
+#[derive(Debug, Clone)]
+pub struct SurfacePosition {
+    #[allow(dead_code)]
+    raw_msg: [u8; 7],
+    msg_type: u8,
+    /// The encoded movement (ground speed)
+    pub movement: u8,
+    /// Ground track in degrees clockwise from true north
+    pub ground_track: Option<f64>,
+    pub cpr_time: u8,
+    pub cpr_format: CprFormat,
+    pub cpr_latitude: u32,
+    pub cpr_longitude: u32,
+}
+
+/// Movement codes where the ground speed quantisation changes and their speeds in knots
+const MOVEMENT_STEPS: [(u8, f64); 7] = [
+    (2, 0.125), (9, 1.0), (13, 2.0), (39, 15.0), (94, 70.0), (109, 100.0), (124, 175.0),
+];
+
+impl SurfacePosition {
+    pub fn new(msg: [u8; 7]) -> Self {
+        let ground_track = if get_bits(&msg, 13, 1) == 1 {
+            Some(get_bits(&msg, 14, 7) as f64 * 360.0 / 128.0)
+        } else {
+            None
+        };
+
+        let cpr_format = if get_bits(&msg, 22, 1) == 1 { CprFormat::Odd } else { CprFormat::Even };
+
+        Self {
+            raw_msg: msg,
+            msg_type: get_bits(&msg, 1, 5) as u8,
+            movement: get_bits(&msg, 6, 7) as u8,
+            ground_track,
+            cpr_time: get_bits(&msg, 21, 1) as u8,
+            cpr_format,
+            cpr_latitude: get_bits(&msg, 23, 17),
+            cpr_longitude: get_bits(&msg, 40, 17),
+        }
+    }
+
+    /// Returns the ground speed in knots decoded from the movement field
+    pub fn get_ground_speed(&self) -> Option<f64> {
+        match self.movement {
+            0 | 125.. => None,
+            1 => Some(0.0),
+            124 => Some(175.0),
+            movement => {
+                let i = MOVEMENT_STEPS.iter().position(|(step, _)| *step > movement)?;
+                let (low_step, low_speed) = MOVEMENT_STEPS[i - 1];
+                let (high_step, high_speed) = MOVEMENT_STEPS[i];
+                let resolution = (high_speed - low_speed) / (high_step - low_step) as f64;
+
+                Some(low_speed + (movement - low_step) as f64 * resolution)
+            }
+        }
+    }
+
+    /// Returns the ground track in degrees
+    pub fn get_ground_track(&self) -> Option<f64> {
+        self.ground_track
+    }
+
+    /// Returns the navigation integrity category and containment radius
+    ///
+    /// `nic_a` - NIC supplement A from the operational status message
+    /// `nic_c` - NIC supplement C from the operational status message
+    ///
+    /// returns the NIC and the containment radius in metres (None if unknown)
+    pub fn get_nic(&self, nic_a: bool, nic_c: bool) -> (u8, Option<f64>) {
+        match (self.msg_type, nic_a, nic_c) {
+            (5, _, _) => (11, Some(7.5)),
+            (6, _, _) => (10, Some(25.0)),
+            (7, true, _) => (9, Some(75.0)),
+            (7, false, _) => (8, Some(185.2)),
+            (8, true, true) => (7, Some(370.4)),
+            (8, true, false) => (6, Some(555.6)),
+            (8, false, true) => (6, Some(1111.2)),
+            _ => (0, None),
+        }
+    }
+
+    /// Returns the cpr format
+    pub fn get_cpr_format(&self) -> CprFormat {
+        self.cpr_format
+    }
+
+    /// Returns the cpr latitude and longitude as a tuple
+    pub fn get_cpr_position(&self) -> (u32, u32) {
+        (self.cpr_latitude, self.cpr_longitude)
+    }
+}
+
+impl AdsbMsg for SurfacePosition {
+    fn msg_id_match(id: u8) -> bool {
+        (5..=8).contains(&id)
+    }
+}
+
+impl std::fmt::Display for SurfacePosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Message:")?;
+        writeln!(f, "Type                : {} (Surface Position)", self.msg_type)?;
+        writeln!(f, "Ground Speed (kt)   : {}", display_option(self.get_ground_speed()))?;
+        writeln!(f, "Ground Track (deg)  : {}", display_option(self.ground_track.map(|v| format!("{:.1}", v))))?;
         writeln!(f, "CPR Time            : {}", self.cpr_time)?;
         writeln!(f, "CPR Format          : {:?}", self.cpr_format)?;
         writeln!(f, "Raw Latitude        : {}", self.cpr_latitude)?;
@@ -144,9 +348,69 @@ impl std::fmt::Display for AircraftPosition {
 pub struct AircraftID {
     _raw_msg: [u8; 7],
     msg_type: u8,
+    pub category: EmitterCategory,
     pub callsign: String
 }
 
+/// Type of aircraft or vehicle transmitting, category sets A to D
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub enum EmitterCategory {
+    NoInformation,
+    Light,
+    Small,
+    Large,
+    HighVortexLarge,
+    Heavy,
+    HighPerformance,
+    Rotorcraft,
+    Glider,
+    LighterThanAir,
+    Parachutist,
+    Ultralight,
+    Uav,
+    SpaceVehicle,
+    EmergencyVehicle,
+    ServiceVehicle,
+    PointObstacle,
+    ClusterObstacle,
+    LineObstacle,
+    Reserved,
+}
+
+impl EmitterCategory {
+    /// Decode the emitter category
+    ///
+    /// `type_code` - the message type code selecting the set (4 is A, 3 is B, 2 is C and 1 is D)
+    /// `category` - the 3 bit category within the set
+    ///
+    /// returns the emitter category
+    pub fn from_code(type_code: u8, category: u8) -> Self {
+        match (type_code, category) {
+            (_, 0) => EmitterCategory::NoInformation,
+            (4, 1) => EmitterCategory::Light,
+            (4, 2) => EmitterCategory::Small,
+            (4, 3) => EmitterCategory::Large,
+            (4, 4) => EmitterCategory::HighVortexLarge,
+            (4, 5) => EmitterCategory::Heavy,
+            (4, 6) => EmitterCategory::HighPerformance,
+            (4, 7) => EmitterCategory::Rotorcraft,
+            (3, 1) => EmitterCategory::Glider,
+            (3, 2) => EmitterCategory::LighterThanAir,
+            (3, 3) => EmitterCategory::Parachutist,
+            (3, 4) => EmitterCategory::Ultralight,
+            (3, 6) => EmitterCategory::Uav,
+            (3, 7) => EmitterCategory::SpaceVehicle,
+            (2, 1) => EmitterCategory::EmergencyVehicle,
+            (2, 2) => EmitterCategory::ServiceVehicle,
+            (2, 3) => EmitterCategory::PointObstacle,
+            (2, 4) => EmitterCategory::ClusterObstacle,
+            (2, 5) => EmitterCategory::LineObstacle,
+            _ => EmitterCategory::Reserved,
+        }
+    }
+}
+
 fn to_6bit_chunks(input: &[u8]) -> Vec<u8> {
     let mut out = Vec::new();
     let mut acc = 0u32;
@@ -169,7 +433,7 @@ fn to_6bit_chunks(input: &[u8]) -> Vec<u8> {
     out
 }
 
-const CHAR_CONVERT: [char; 64] = [
+pub(crate) const CHAR_CONVERT: [char; 64] = [
     '#', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q',
     'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '#', '#', '#', '#', '#', '_', '#', '#', '#',
     '#', '#', '#', '#', '#', '#', '#', '#', '#', '#', '#', '#', '0', '1', '2', '3', '4', '5',
@@ -190,11 +454,12 @@ impl AircraftID {
         }
 
         let msg_type = (msg[0] & 0b1111_1000) >> 3;
-
+        let category = EmitterCategory::from_code(msg_type, msg[0] & 0b0000_0111);
 
         Self {
             _raw_msg: msg,
             msg_type: msg_type,
+            category: category,
             callsign: callsign
         }
 
@@ -203,6 +468,11 @@ impl AircraftID {
     pub fn get_callsign(&self) -> String {
         self.callsign.clone()
     }
+
+    /// Returns the emitter category of the aircraft
+    pub fn get_category(&self) -> EmitterCategory {
+        self.category
+    }
 }
 
 impl AdsbMsg for AircraftID {
@@ -216,12 +486,563 @@ impl std::fmt::Display for AircraftID {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Message:")?;
         writeln!(f, "Type                : {} (ID)", self.msg_type)?;
+        writeln!(f, "Category            : {:?}", self.category)?;
         writeln!(f, "Callsign            : {}", self.callsign)?;
 
         Ok(())
     }
 }
 
+/// Source of the vertical rate in a velocity message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VerticalRateSource {
+    Gnss,
+    Barometric,
+}
+
+/// Airspeed type in an airspeed velocity message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AirspeedType {
+    Indicated,
+    True,
+}
+
+/// Airborne velocity message
+#[derive(Debug, Clone)]
+pub struct AirborneVelocity {
+    #[allow(dead_code)]
+    raw_msg: [u8; 7],
+    msg_type: u8,
+    /// 1-2 ground speed, 3-4 airspeed (2 and 4 are supersonic)
+    pub sub_type: u8,
+    pub intent_change: bool,
+    pub ifr_capability: bool,
+    pub nac_v: u8,
+    /// East velocity in knots (negative is west)
+    pub velocity_ew: Option<i32>,
+    /// North velocity in knots (negative is south)
+    pub velocity_ns: Option<i32>,
+    /// Magnetic heading in degrees
+    pub heading: Option<f64>,
+    /// Airspeed in knots
+    pub airspeed: Option<i32>,
+    pub airspeed_type: Option<AirspeedType>,
+    pub vertical_rate_source: VerticalRateSource,
+    /// Vertical rate in feet per minute
+    pub vertical_rate: Option<i32>,
+    /// GNSS altitude minus barometric altitude in feet
+    pub gnss_baro_diff: Option<i32>,
+}
+
+impl AirborneVelocity {
+    pub fn new(msg: [u8; 7]) -> Self {
+        let msg_type = get_bits(&msg, 1, 5) as u8;
+        let sub_type = get_bits(&msg, 6, 3) as u8;
+        let supersonic = sub_type == 2 || sub_type == 4;
+        let speed_scale = if supersonic { 4 } else { 1 };
+
+        let mut velocity_ew = None;
+        let mut velocity_ns = None;
+        let mut heading = None;
+        let mut airspeed = None;
+        let mut airspeed_type = None;
+
+        match sub_type {
+            1 | 2 => {
+                let raw_ew = get_bits(&msg, 15, 10) as i32;
+                let raw_ns = get_bits(&msg, 26, 10) as i32;
+
+                if raw_ew != 0 && raw_ns != 0 {
+                    let sign_ew = if get_bits(&msg, 14, 1) == 1 { -1 } else { 1 };
+                    let sign_ns = if get_bits(&msg, 25, 1) == 1 { -1 } else { 1 };
+
+                    velocity_ew = Some(sign_ew * (raw_ew - 1) * speed_scale);
+                    velocity_ns = Some(sign_ns * (raw_ns - 1) * speed_scale);
+                }
+            },
+            3 | 4 => {
+                if get_bits(&msg, 14, 1) == 1 {
+                    heading = Some(get_bits(&msg, 15, 10) as f64 * 360.0 / 1024.0);
+                }
+
+                let raw_airspeed = get_bits(&msg, 26, 10) as i32;
+                if raw_airspeed != 0 {
+                    airspeed = Some((raw_airspeed - 1) * speed_scale);
+                }
+
+                airspeed_type = Some(if get_bits(&msg, 25, 1) == 1 { AirspeedType::True } else { AirspeedType::Indicated });
+            },
+            _ => (),
+        }
+
+        let vertical_rate_source = if get_bits(&msg, 36, 1) == 1 {
+            VerticalRateSource::Barometric
+        } else {
+            VerticalRateSource::Gnss
+        };
+
+        let raw_vertical_rate = get_bits(&msg, 38, 9) as i32;
+        let vertical_rate = if raw_vertical_rate == 0 {
+            None
+        } else {
+            let sign = if get_bits(&msg, 37, 1) == 1 { -1 } else { 1 };
+            Some(sign * (raw_vertical_rate - 1) * 64)
+        };
+
+        let raw_diff = get_bits(&msg, 50, 7) as i32;
+        let gnss_baro_diff = if raw_diff == 0 {
+            None
+        } else {
+            let sign = if get_bits(&msg, 49, 1) == 1 { -1 } else { 1 };
+            Some(sign * (raw_diff - 1) * 25)
+        };
+
+        Self {
+            raw_msg: msg,
+            msg_type,
+            sub_type,
+            intent_change: get_bits(&msg, 9, 1) == 1,
+            ifr_capability: get_bits(&msg, 10, 1) == 1,
+            nac_v: get_bits(&msg, 11, 3) as u8,
+            velocity_ew,
+            velocity_ns,
+            heading,
+            airspeed,
+            airspeed_type,
+            vertical_rate_source,
+            vertical_rate,
+            gnss_baro_diff,
+        }
+    }
+
+    /// Returns the ground speed in knots
+    pub fn get_ground_speed(&self) -> Option<f64> {
+        let (ew, ns) = (self.velocity_ew? as f64, self.velocity_ns? as f64);
+
+        Some((ew * ew + ns * ns).sqrt())
+    }
+
+    /// Returns the track angle in degrees clockwise from true north
+    pub fn get_track(&self) -> Option<f64> {
+        let (ew, ns) = (self.velocity_ew? as f64, self.velocity_ns? as f64);
+
+        Some(ew.atan2(ns).to_degrees().rem_euclid(360.0))
+    }
+
+    /// Returns the vertical rate in feet per minute
+    pub fn get_vertical_rate(&self) -> Option<i32> {
+        self.vertical_rate
+    }
+}
+
+impl AdsbMsg for AirborneVelocity {
+    fn msg_id_match(id: u8) -> bool {
+        id == 19
+    }
+}
+
+/// Format an optional value for display
+fn display_option<T: std::fmt::Display>(value: Option<T>) -> String {
+    match value {
+        Some(value) => format!("{}", value),
+        None => String::from("n/a"),
+    }
+}
+
+impl std::fmt::Display for AirborneVelocity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Message:")?;
+        writeln!(f, "Type                : {} (Velocity)", self.msg_type)?;
+        writeln!(f, "Sub Type            : {}", self.sub_type)?;
+        writeln!(f, "Intent Change       : {}", self.intent_change)?;
+        writeln!(f, "IFR Capability      : {}", self.ifr_capability)?;
+        writeln!(f, "NACv                : {}", self.nac_v)?;
+        if self.sub_type <= 2 {
+            writeln!(f, "Ground Speed (kt)   : {}", display_option(self.get_ground_speed().map(|v| format!("{:.1}", v))))?;
+            writeln!(f, "Track (deg)         : {}", display_option(self.get_track().map(|v| format!("{:.1}", v))))?;
+        } else {
+            writeln!(f, "Heading (deg)       : {}", display_option(self.heading.map(|v| format!("{:.1}", v))))?;
+            writeln!(f, "Airspeed (kt)       : {}", display_option(self.airspeed))?;
+            writeln!(f, "Airspeed Type       : {}", display_option(self.airspeed_type.map(|v| format!("{:?}", v))))?;
+        }
+        writeln!(f, "Vertical Rate (fpm) : {}", display_option(self.vertical_rate))?;
+        writeln!(f, "Vertical Rate Src   : {:?}", self.vertical_rate_source)?;
+        writeln!(f, "GNSS - Baro (ft)    : {}", display_option(self.gnss_baro_diff))?;
+        Ok(())
+    }
+}
+
+/// Reference direction of reported headings and tracks
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub enum HeadingReference {
+    TrueNorth,
+    MagneticNorth,
+}
+
+/// Aircraft operational status message
+#[derive(Debug, Clone)]
+pub struct OperationalStatus {
+    raw_msg: [u8; 7],
+    msg_type: u8,
+    /// 0 airborne, 1 surface
+    pub sub_type: u8,
+    /// Capability class codes (12 bits for surface)
+    pub capability_class: u16,
+    pub operational_mode: u16,
+    /// ADS-B version number (0, 1 or 2)
+    pub version: u8,
+    pub nic_supplement_a: bool,
+    /// Only reported by surface aircraft
+    pub nic_supplement_c: bool,
+    pub nac_p: u8,
+    /// Geometric vertical accuracy (airborne only)
+    pub gva: Option<u8>,
+    pub sil: u8,
+    /// True if SIL is per sample rather than per hour
+    pub sil_per_sample: bool,
+    /// Barometric altitude cross checked (airborne only)
+    pub nic_baro: Option<bool>,
+    pub heading_reference: HeadingReference,
+}
+
+impl OperationalStatus {
+    pub fn new(msg: [u8; 7]) -> Self {
+        let sub_type = get_bits(&msg, 6, 3) as u8;
+        let surface = sub_type == 1;
+
+        let capability_class = if surface { get_bits(&msg, 9, 12) } else { get_bits(&msg, 9, 16) } as u16;
+        let heading_reference = if get_bits(&msg, 54, 1) == 1 {
+            HeadingReference::MagneticNorth
+        } else {
+            HeadingReference::TrueNorth
+        };
+
+        Self {
+            raw_msg: msg,
+            msg_type: get_bits(&msg, 1, 5) as u8,
+            sub_type,
+            capability_class,
+            operational_mode: get_bits(&msg, 25, 16) as u16,
+            version: get_bits(&msg, 41, 3) as u8,
+            nic_supplement_a: get_bits(&msg, 44, 1) == 1,
+            nic_supplement_c: surface && get_bits(&msg, 20, 1) == 1,
+            nac_p: get_bits(&msg, 45, 4) as u8,
+            gva: if surface { None } else { Some(get_bits(&msg, 49, 2) as u8) },
+            sil: get_bits(&msg, 51, 2) as u8,
+            sil_per_sample: get_bits(&msg, 55, 1) == 1,
+            nic_baro: if surface { None } else { Some(get_bits(&msg, 53, 1) == 1) },
+            heading_reference,
+        }
+    }
+
+    /// Returns true if the aircraft has an operational TCAS (airborne only)
+    pub fn is_tcas_operational(&self) -> bool {
+        self.sub_type == 0 && get_bits(&self.raw_msg, 11, 1) == 1
+    }
+
+    /// Returns true if the aircraft can receive 1090 MHz extended squitter
+    pub fn has_1090es_in(&self) -> bool {
+        get_bits(&self.raw_msg, 12, 1) == 1
+    }
+
+    /// Returns true if the aircraft can receive UAT
+    pub fn has_uat_in(&self) -> bool {
+        let bit = if self.sub_type == 1 { 16 } else { 19 };
+
+        get_bits(&self.raw_msg, bit, 1) == 1
+    }
+
+    /// Returns true if a TCAS resolution advisory is active
+    pub fn is_tcas_ra_active(&self) -> bool {
+        get_bits(&self.raw_msg, 27, 1) == 1
+    }
+
+    /// Returns true if the IDENT switch is active
+    pub fn is_ident_active(&self) -> bool {
+        get_bits(&self.raw_msg, 28, 1) == 1
+    }
+}
+
+impl AdsbMsg for OperationalStatus {
+    fn msg_id_match(id: u8) -> bool {
+        id == 31
+    }
+}
+
+impl std::fmt::Display for OperationalStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Message:")?;
+        writeln!(f, "Type                : {} (Operational Status)", self.msg_type)?;
+        writeln!(f, "Sub Type            : {}", if self.sub_type == 1 { "Surface" } else { "Airborne" })?;
+        writeln!(f, "ADS-B Version       : {}", self.version)?;
+        writeln!(f, "Capability Class    : {:04X}", self.capability_class)?;
+        writeln!(f, "Operational Mode    : {:04X}", self.operational_mode)?;
+        writeln!(f, "TCAS Operational    : {}", self.is_tcas_operational())?;
+        writeln!(f, "1090ES In           : {}", self.has_1090es_in())?;
+        writeln!(f, "UAT In              : {}", self.has_uat_in())?;
+        writeln!(f, "TCAS RA Active      : {}", self.is_tcas_ra_active())?;
+        writeln!(f, "IDENT Active        : {}", self.is_ident_active())?;
+        writeln!(f, "NIC Supplement A    : {}", self.nic_supplement_a)?;
+        writeln!(f, "NIC Supplement C    : {}", self.nic_supplement_c)?;
+        writeln!(f, "NACp                : {}", self.nac_p)?;
+        writeln!(f, "GVA                 : {}", display_option(self.gva))?;
+        writeln!(f, "SIL                 : {}", self.sil)?;
+        writeln!(f, "SIL Per Sample      : {}", self.sil_per_sample)?;
+        writeln!(f, "NIC Baro            : {}", display_option(self.nic_baro))?;
+        writeln!(f, "Heading Reference   : {:?}", self.heading_reference)?;
+        Ok(())
+    }
+}
+
+/// Emergency or priority state broadcast by an aircraft
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub enum EmergencyState {
+    NoEmergency,
+    General,
+    Medical,
+    MinimumFuel,
+    NoCommunications,
+    UnlawfulInterference,
+    Downed,
+    Reserved,
+}
+
+impl EmergencyState {
+    fn from_code(code: u32) -> Self {
+        match code {
+            0 => EmergencyState::NoEmergency,
+            1 => EmergencyState::General,
+            2 => EmergencyState::Medical,
+            3 => EmergencyState::MinimumFuel,
+            4 => EmergencyState::NoCommunications,
+            5 => EmergencyState::UnlawfulInterference,
+            6 => EmergencyState::Downed,
+            _ => EmergencyState::Reserved,
+        }
+    }
+}
+
+impl std::fmt::Display for EmergencyState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            EmergencyState::NoEmergency => "none",
+            EmergencyState::General => "general",
+            EmergencyState::Medical => "medical",
+            EmergencyState::MinimumFuel => "min fuel",
+            EmergencyState::NoCommunications => "no comms",
+            EmergencyState::UnlawfulInterference => "unlawful",
+            EmergencyState::Downed => "downed",
+            EmergencyState::Reserved => "reserved",
+        };
+
+        write!(f, "{}", text)
+    }
+}
+
+/// ACAS resolution advisory broadcast by an aircraft
+#[derive(Debug, Clone)]
+pub struct ResolutionAdvisory {
+    /// Active resolution advisories
+    pub active_ra: u16,
+    /// Resolution advisory complements
+    pub ra_complement: u8,
+    /// True if the resolution advisory has been terminated
+    pub terminated: bool,
+    /// True if there are multiple threats
+    pub multiple_threat: bool,
+    /// Threat type indicator: 1 for an ICAO address, 2 for altitude, range and bearing
+    pub threat_type: u8,
+    /// Threat identity data
+    pub threat_identity: u32,
+}
+
+/// Aircraft status message holding the emergency state or a resolution advisory
+#[derive(Debug, Clone)]
+pub struct AircraftStatus {
+    msg_type: u8,
+    /// 1 for emergency status, 2 for resolution advisory
+    pub sub_type: u8,
+    pub emergency: Option<EmergencyState>,
+    /// Mode A code with each octal digit in 3 bits
+    pub squawk: Option<u16>,
+    pub resolution_advisory: Option<ResolutionAdvisory>,
+}
+
+impl AircraftStatus {
+    pub fn new(msg: [u8; 7]) -> Self {
+        let sub_type = get_bits(&msg, 6, 3) as u8;
+
+        let (emergency, squawk) = match sub_type {
+            1 => (Some(EmergencyState::from_code(get_bits(&msg, 9, 3))), Some(decode_id13(get_bits(&msg, 12, 13) as u16))),
+            _ => (None, None),
+        };
+
+        let resolution_advisory = match sub_type {
+            2 => Some(ResolutionAdvisory {
+                active_ra: get_bits(&msg, 9, 14) as u16,
+                ra_complement: get_bits(&msg, 23, 4) as u8,
+                terminated: get_bits(&msg, 27, 1) == 1,
+                multiple_threat: get_bits(&msg, 28, 1) == 1,
+                threat_type: get_bits(&msg, 29, 2) as u8,
+                threat_identity: get_bits(&msg, 31, 26),
+            }),
+            _ => None,
+        };
+
+        Self {
+            msg_type: get_bits(&msg, 1, 5) as u8,
+            sub_type,
+            emergency,
+            squawk,
+            resolution_advisory,
+        }
+    }
+}
+
+impl AdsbMsg for AircraftStatus {
+    fn msg_id_match(id: u8) -> bool {
+        id == 28
+    }
+}
+
+impl std::fmt::Display for AircraftStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Message:")?;
+        writeln!(f, "Type                : {} (Aircraft Status)", self.msg_type)?;
+        writeln!(f, "Sub Type            : {}", self.sub_type)?;
+        writeln!(f, "Emergency           : {}", display_option(self.emergency))?;
+        writeln!(f, "Squawk              : {}", self.squawk.map_or_else(|| "n/a".to_string(), |s| format!("{:04o}", s)))?;
+        if let Some(ra) = &self.resolution_advisory {
+            writeln!(f, "Active RA           : {:014b}", ra.active_ra)?;
+            writeln!(f, "RA Complement       : {:04b}", ra.ra_complement)?;
+            writeln!(f, "RA Terminated       : {}", ra.terminated)?;
+            writeln!(f, "Multiple Threat     : {}", ra.multiple_threat)?;
+            writeln!(f, "Threat Type         : {}", ra.threat_type)?;
+            writeln!(f, "Threat Identity     : {:07X}", ra.threat_identity)?;
+        }
+        Ok(())
+    }
+}
+
+/// Source of the selected altitude
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectedAltitudeSource {
+    /// Mode control panel or flight control unit
+    McpFcu,
+    /// Flight management system
+    Fms,
+}
+
+/// Autopilot modes engaged on the aircraft
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct AutopilotModes {
+    pub autopilot: bool,
+    pub vnav: bool,
+    pub altitude_hold: bool,
+    pub approach: bool,
+    pub lnav: bool,
+}
+
+/// Target state and status message, only subtype 1 (ADS-B version 2) is decoded
+#[derive(Debug, Clone)]
+pub struct TargetStateStatus {
+    msg_type: u8,
+    pub sub_type: u8,
+    /// Selected altitude in feet
+    pub selected_altitude: Option<i32>,
+    pub selected_altitude_source: SelectedAltitudeSource,
+    /// Barometric pressure setting in millibars
+    pub baro_setting: Option<f64>,
+    /// Selected heading in degrees
+    pub selected_heading: Option<f64>,
+    pub nac_p: u8,
+    pub nic_baro: bool,
+    pub sil: u8,
+    /// None if the mode flags are not reported
+    pub autopilot_modes: Option<AutopilotModes>,
+    pub tcas_operational: bool,
+}
+
+impl TargetStateStatus {
+    pub fn new(msg: [u8; 7]) -> Self {
+        let sub_type = get_bits(&msg, 6, 2) as u8;
+        let version_2 = sub_type == 1;
+
+        let selected_altitude_source = if get_bits(&msg, 9, 1) == 1 {
+            SelectedAltitudeSource::Fms
+        } else {
+            SelectedAltitudeSource::McpFcu
+        };
+
+        let selected_altitude = match get_bits(&msg, 10, 11) {
+            0 => None,
+            n => Some((n as i32 - 1) * 32),
+        };
+
+        let baro_setting = match get_bits(&msg, 21, 9) {
+            0 => None,
+            n => Some((n - 1) as f64 * 0.8 + 800.0),
+        };
+
+        let selected_heading = match get_bits(&msg, 30, 1) {
+            1 => Some(get_bits(&msg, 31, 9) as f64 * 180.0 / 256.0),
+            _ => None,
+        };
+
+        let autopilot_modes = match get_bits(&msg, 47, 1) {
+            1 => Some(AutopilotModes {
+                autopilot: get_bits(&msg, 48, 1) == 1,
+                vnav: get_bits(&msg, 49, 1) == 1,
+                altitude_hold: get_bits(&msg, 50, 1) == 1,
+                approach: get_bits(&msg, 52, 1) == 1,
+                lnav: get_bits(&msg, 54, 1) == 1,
+            }),
+            _ => None,
+        };
+
+        Self {
+            msg_type: get_bits(&msg, 1, 5) as u8,
+            sub_type,
+            selected_altitude: selected_altitude.filter(|_| version_2),
+            selected_altitude_source,
+            baro_setting: baro_setting.filter(|_| version_2),
+            selected_heading: selected_heading.filter(|_| version_2),
+            nac_p: get_bits(&msg, 40, 4) as u8,
+            nic_baro: get_bits(&msg, 44, 1) == 1,
+            sil: get_bits(&msg, 45, 2) as u8,
+            autopilot_modes: autopilot_modes.filter(|_| version_2),
+            tcas_operational: version_2 && get_bits(&msg, 53, 1) == 1,
+        }
+    }
+}
+
+impl AdsbMsg for TargetStateStatus {
+    fn msg_id_match(id: u8) -> bool {
+        id == 29
+    }
+}
+
+impl std::fmt::Display for TargetStateStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Message:")?;
+        writeln!(f, "Type                : {} (Target State and Status)", self.msg_type)?;
+        writeln!(f, "Sub Type            : {}", self.sub_type)?;
+        writeln!(f, "Selected Altitude   : {} ({:?})", display_option(self.selected_altitude), self.selected_altitude_source)?;
+        writeln!(f, "Baro Setting        : {}", display_option(self.baro_setting))?;
+        writeln!(f, "Selected Heading    : {}", display_option(self.selected_heading))?;
+        writeln!(f, "NACp                : {}", self.nac_p)?;
+        writeln!(f, "NIC Baro            : {}", self.nic_baro)?;
+        writeln!(f, "SIL                 : {}", self.sil)?;
+        writeln!(f, "Autopilot Modes     : {:?}", self.autopilot_modes)?;
+        writeln!(f, "TCAS Operational    : {}", self.tcas_operational)?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -242,20 +1063,55 @@ mod tests {
         assert_eq!(id.msg_type, 4);
     }
 
+    #[test]
+    fn test_aircraft_id_category() {
+        let data: [u8; 7] = [0x20, 0x2C, 0xC3, 0x71, 0xC3, 0x2C, 0xE0];
+        assert_eq!(AircraftID::new(data).get_category(), EmitterCategory::NoInformation);
+
+        let data: [u8; 7] = [0x25, 0x2C, 0xC3, 0x71, 0xC3, 0x2C, 0xE0];
+        assert_eq!(AircraftID::new(data).get_category(), EmitterCategory::Heavy);
+
+        assert_eq!(EmitterCategory::from_code(4, 7), EmitterCategory::Rotorcraft);
+        assert_eq!(EmitterCategory::from_code(3, 1), EmitterCategory::Glider);
+        assert_eq!(EmitterCategory::from_code(3, 6), EmitterCategory::Uav);
+        assert_eq!(EmitterCategory::from_code(2, 2), EmitterCategory::ServiceVehicle);
+        assert_eq!(EmitterCategory::from_code(1, 3), EmitterCategory::Reserved);
+    }
+
     #[test]
     fn test_aircraft_position_alt_25() {
         let data: [u8; 7] = [0x58, 0xC3, 0x82, 0xD6, 0x90, 0xC8, 0xAC];
 
         let pos = AircraftPosition::new(data);
-        assert_eq!(pos.altitude, 38000);
+        assert_eq!(pos.altitude, Some(38000));
     }
 
     #[test]
-    fn test_aircraft_position_alt_100() {
+    fn test_aircraft_position_alt_gillham() {
         let data: [u8; 7] = [0x58, 0xC2, 0x82, 0xD6, 0x90, 0xC8, 0xAC];
 
         let pos = AircraftPosition::new(data);
-        assert_eq!(pos.altitude, 155000);
+        assert_eq!(pos.altitude, Some(28300));
+    }
+
+    #[test]
+    fn test_aircraft_position_alt_unknown() {
+        let data: [u8; 7] = [0x58, 0x00, 0x02, 0xD6, 0x90, 0xC8, 0xAC];
+
+        let pos = AircraftPosition::new(data);
+        assert_eq!(pos.altitude, None);
+    }
+
+    #[test]
+    fn test_aircraft_position_gnss_alt() {
+        let data: [u8; 7] = [0xA0, 0x3E, 0x82, 0xD6, 0x90, 0xC8, 0xAC];
+
+        let pos = AircraftPosition::new(data);
+        assert_eq!(pos.msg_type, 20);
+        assert_eq!(pos.altitude_source, AltitudeSource::Gnss);
+        assert_eq!(pos.altitude, Some(3281));
+        assert!(AircraftPosition::msg_id_match(22));
+        assert!(!AircraftPosition::msg_id_match(19));
     }
 
     #[test]
@@ -263,7 +1119,7 @@ mod tests {
         let data: [u8; 7] = [0x58, 0x01, 0x02, 0xD6, 0x90, 0xC8, 0xAC];
 
         let pos = AircraftPosition::new(data);
-        assert_eq!(pos.altitude, -1000);
+        assert_eq!(pos.altitude, Some(-1000));
     }
 
     #[test]
@@ -271,7 +1127,7 @@ mod tests {
         let data: [u8; 7] = [0x58, 0x01, 0x12, 0xD6, 0x90, 0xC8, 0xAC];
 
         let pos = AircraftPosition::new(data);
-        assert_eq!(pos.altitude, -975);
+        assert_eq!(pos.altitude, Some(-975));
     }
 
     #[test]
@@ -319,4 +1175,153 @@ mod tests {
         assert_eq!(pos.cpr_latitude, 74158);
         assert_eq!(pos.cpr_longitude, 50194);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_airborne_velocity_ground_speed() {
+        let data: [u8; 7] = [0x99, 0x44, 0x09, 0x94, 0x08, 0x38, 0x17];
+
+        let vel = AirborneVelocity::new(data);
+
+        assert_eq!(vel.msg_type, 19);
+        assert_eq!(vel.sub_type, 1);
+        assert!((vel.get_ground_speed().unwrap() - 159.20).abs() < 0.01);
+        assert!((vel.get_track().unwrap() - 182.88).abs() < 0.01);
+        assert_eq!(vel.vertical_rate, Some(-832));
+        assert_eq!(vel.vertical_rate_source, VerticalRateSource::Gnss);
+        assert_eq!(vel.gnss_baro_diff, Some(550));
+    }
+
+    #[test]
+    fn test_airborne_velocity_airspeed() {
+        let data: [u8; 7] = [0x9B, 0x06, 0xB6, 0xAF, 0x18, 0x94, 0x00];
+
+        let vel = AirborneVelocity::new(data);
+
+        assert_eq!(vel.sub_type, 3);
+        assert!((vel.heading.unwrap() - 243.98).abs() < 0.01);
+        assert_eq!(vel.airspeed, Some(375));
+        assert_eq!(vel.airspeed_type, Some(AirspeedType::True));
+        assert_eq!(vel.vertical_rate, Some(-2304));
+        assert_eq!(vel.vertical_rate_source, VerticalRateSource::Barometric);
+        assert_eq!(vel.get_ground_speed(), None);
+    }
+
+    #[test]
+    fn test_surface_position_movement() {
+        let data: [u8; 7] = [0x3A, 0x9A, 0x15, 0x32, 0x37, 0xAE, 0xF0];
+
+        let pos = SurfacePosition::new(data);
+
+        assert_eq!(pos.msg_type, 7);
+        assert!((pos.get_ground_speed().unwrap() - 17.0).abs() < 0.01);
+        assert!((pos.get_ground_track().unwrap() - 92.8125).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_surface_position_cpr() {
+        let data: [u8; 7] = [0x3A, 0xAB, 0x23, 0x87, 0x33, 0xC8, 0xCD];
+
+        let pos = SurfacePosition::new(data);
+
+        assert_eq!(pos.cpr_format, CprFormat::Even);
+        assert_eq!(pos.get_cpr_position(), (115609, 116941));
+    }
+
+    #[test]
+    fn test_operational_status_airborne() {
+        let data: [u8; 7] = [0xF8, 0x30, 0x00, 0x00, 0x00, 0x5A, 0xB8];
+
+        let status = OperationalStatus::new(data);
+
+        assert_eq!(status.msg_type, 31);
+        assert_eq!(status.sub_type, 0);
+        assert_eq!(status.version, 2);
+        assert!(status.nic_supplement_a);
+        assert!(!status.nic_supplement_c);
+        assert_eq!(status.nac_p, 10);
+        assert_eq!(status.gva, Some(2));
+        assert_eq!(status.sil, 3);
+        assert_eq!(status.nic_baro, Some(true));
+        assert_eq!(status.heading_reference, HeadingReference::TrueNorth);
+        assert!(status.is_tcas_operational());
+        assert!(status.has_1090es_in());
+    }
+
+    #[test]
+    fn test_operational_status_surface() {
+        let data: [u8; 7] = [0xF9, 0x00, 0x10, 0x00, 0x00, 0x59, 0x24];
+
+        let status = OperationalStatus::new(data);
+
+        assert_eq!(status.sub_type, 1);
+        assert!(status.nic_supplement_a);
+        assert!(status.nic_supplement_c);
+        assert_eq!(status.nac_p, 9);
+        assert_eq!(status.gva, None);
+        assert_eq!(status.sil, 2);
+        assert_eq!(status.heading_reference, HeadingReference::MagneticNorth);
+    }
+
+    #[test]
+    fn test_position_nic() {
+        let pos = AircraftPosition::new([0x58, 0xC3, 0x82, 0xD6, 0x90, 0xC8, 0xAC]);
+        assert_eq!(pos.get_nic(false), (8, Some(185.2)));
+
+        let pos = AircraftPosition::new([0x59, 0xC3, 0x82, 0xD6, 0x90, 0xC8, 0xAC]);
+        assert_eq!(pos.get_nic(true), (9, Some(75.0)));
+
+        let pos = SurfacePosition::new([0x42, 0xAB, 0x23, 0x87, 0x33, 0xC8, 0xCD]);
+        assert_eq!(pos.get_nic(false, true), (6, Some(1111.2)));
+        assert_eq!(pos.get_nic(false, false), (0, None));
+    }
+
+    #[test]
+    fn test_aircraft_status_emergency() {
+        let status = AircraftStatus::new([0xE1, 0x2A, 0xAA, 0x00, 0x00, 0x00, 0x00]);
+
+        assert_eq!(status.sub_type, 1);
+        assert_eq!(status.emergency, Some(EmergencyState::General));
+        assert_eq!(status.squawk, Some(0o7700));
+        assert!(status.resolution_advisory.is_none());
+
+        let status = AircraftStatus::new([0xE1, 0x08, 0x08, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(status.emergency, Some(EmergencyState::NoEmergency));
+        assert_eq!(status.squawk, Some(0o1200));
+    }
+
+    #[test]
+    fn test_aircraft_status_resolution_advisory() {
+        let status = AircraftStatus::new([0xE2, 0x80, 0x00, 0x24, 0x12, 0x34, 0x56]);
+        let ra = status.resolution_advisory.unwrap();
+
+        assert_eq!(status.emergency, None);
+        assert_eq!(ra.active_ra, 0x2000);
+        assert!(ra.terminated);
+        assert!(!ra.multiple_threat);
+        assert_eq!(ra.threat_type, 1);
+        assert_eq!(ra.threat_identity, 0x123456);
+    }
+
+    #[test]
+    fn test_target_state_status() {
+        let state = TargetStateStatus::new([0xEA, 0x21, 0x48, 0x5C, 0xBF, 0x3F, 0x8C]);
+
+        assert_eq!(state.msg_type, 29);
+        assert_eq!(state.sub_type, 1);
+        assert_eq!(state.selected_altitude, Some(16992));
+        assert_eq!(state.selected_altitude_source, SelectedAltitudeSource::McpFcu);
+        assert!((state.baro_setting.unwrap() - 1012.8).abs() < 0.01);
+        assert!((state.selected_heading.unwrap() - 66.797).abs() < 0.01);
+        assert_eq!(state.nac_p, 9);
+        assert!(state.nic_baro);
+        assert_eq!(state.sil, 3);
+        assert!(state.tcas_operational);
+
+        let modes = state.autopilot_modes.unwrap();
+        assert!(modes.autopilot);
+        assert!(modes.vnav);
+        assert!(!modes.altitude_hold);
+        assert!(!modes.approach);
+        assert!(modes.lnav);
+    }
+}
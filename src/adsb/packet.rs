@@ -2,18 +2,89 @@
 /// 
 /// Author Jack Duignan (JackpDuignan@gmail.com)
 
-use chrono::Local;
+use std::collections::HashMap;
 
-use crate::adsb::msgs::{AdsbMsg, AdsbMsgType, AircraftPosition, AircraftID, UknownMsg};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::adsb::commb::CommB;
+use crate::adsb::crc::get_syndrome;
+use crate::adsb::modeac::{decode_ac13, decode_id13};
+use crate::adsb::msgs::{AdsbMsg, AdsbMsgType, AircraftPosition, AircraftID, AirborneVelocity, SurfacePosition, OperationalStatus, AircraftStatus, TargetStateStatus, UknownMsg};
+
+/// Flag set on addresses that are not ICAO aircraft addresses so they can't collide with them
+pub const NON_ICAO_ADDRESS: u32 = 1 << 24;
+
+/// Kind of address used to identify a target
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub enum AddressType {
+    Icao,
+    /// Anonymous, ground vehicle or TIS-B track file address
+    NonIcao,
+}
+
+impl AddressType {
+    /// Get the type of an address
+    ///
+    /// `address` - the address including the non ICAO flag
+    pub fn from_address(address: u32) -> Self {
+        if address & NON_ICAO_ADDRESS != 0 {
+            AddressType::NonIcao
+        } else {
+            AddressType::Icao
+        }
+    }
+}
+
+/// Format an address as hex with a leading ~ for non ICAO addresses
+///
+/// `address` - the address including the non ICAO flag
+pub fn format_address(address: u32) -> String {
+    match AddressType::from_address(address) {
+        AddressType::Icao => format!("{:06x}", address),
+        AddressType::NonIcao => format!("~{:06x}", address & !NON_ICAO_ADDRESS),
+    }
+}
+
+/// System a frame was received from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub enum DataSource {
+    /// DF17 extended squitter from a transponder
+    Adsb,
+    /// DF18 extended squitter from a non-transponder device such as a ground vehicle
+    AdsbNonTransponder,
+    /// DF18 traffic information rebroadcast by a ground station
+    TisB,
+    /// DF18 ADS-B rebroadcast from another link by a ground station
+    AdsR,
+    /// Mode S reply to an interrogation
+    ModeS,
+}
 
 #[derive(Debug, Clone)]
 pub struct AdsbPacket {
     packet: Vec<u8>,
     downlink_format: u8,
+    /// Capability or the control field for DF18
     capability: u8,
+    /// Address of the target with `NON_ICAO_ADDRESS` set if it is not an ICAO address
     pub icao: u32,
+    pub source: DataSource,
     pub msg_type: u8,
     pub msg: AdsbMsgType,
+    /// Number of bits repaired by error correction
+    pub corrected_bits: usize,
+    /// Signal power of the frame in dBFS, None if not received over the air
+    pub signal_power: Option<f64>,
+    /// Noise floor before the frame in dBFS
+    pub noise_power: Option<f64>,
+    /// Ticks of a 12 MHz clock from the start of the sample stream to the start of the frame,
+    /// None if not received from a sample stream
+    pub timestamp: Option<u64>,
+    /// Time the frame was received, taken from the sample stream when received over the air
     pub time_processed: chrono::prelude::DateTime<Local>
 }
 
@@ -24,8 +95,55 @@ impl AdsbPacket {
     /// 
     pub fn new(packet: Vec<u8>) -> AdsbPacket{
         let downlink_format = packet[0] >> 3;
-        let capability = packet[0] & 5;
-        let icao: u32 = (packet[1] as u32) << 16 | (packet[2] as u32) << 8 | packet[3] as u32;
+        let capability = packet[0] & 7;
+        let mut icao: u32 = if AdsbPacket::is_address_parity_format(downlink_format) {
+            // The address is XORed into the parity so a correct frame leaves it as the syndrome
+            get_syndrome(&packet)
+        } else {
+            (packet[1] as u32) << 16 | (packet[2] as u32) << 8 | packet[3] as u32
+        };
+
+        // DF18 reuses the ME field of DF17 except for coarse TIS-B, management and reserved formats
+        let (source, non_icao, adsb_payload) = match (downlink_format, capability) {
+            (17, _) => (DataSource::Adsb, false, true),
+            (18, 0) => (DataSource::AdsbNonTransponder, false, true),
+            (18, 1) => (DataSource::AdsbNonTransponder, true, true),
+            (18, 2) => (DataSource::TisB, false, true),
+            (18, 5) => (DataSource::TisB, true, true),
+            (18, 6) => (DataSource::AdsR, false, true),
+            // Coarse TIS-B, TIS-B/ADS-R management and reserved
+            (18, _) => (DataSource::TisB, false, false),
+            _ => (DataSource::ModeS, false, false),
+        };
+
+        if non_icao || (adsb_payload && matches!(capability, 2 | 6) && AdsbPacket::is_imf_set(&packet)) {
+            icao |= NON_ICAO_ADDRESS;
+        }
+
+        // Only extended squitters carry an ME field, short frames are kept raw
+        if !adsb_payload || packet.len() < 4 + 7 {
+            let msg = match downlink_format {
+                11 => AdsbMsgType::AllCallReply(AllCallReply::new(&packet)),
+                0 | 4 | 5 | 16 | 20 | 21 => AdsbMsgType::SurveillanceReply(SurveillanceReply::new(&packet)),
+                _ => AdsbMsgType::Uknown(UknownMsg {raw_msg: packet[1..packet.len()].to_vec()}),
+            };
+
+            return Self {
+                msg: msg,
+                packet: packet,
+                downlink_format: downlink_format,
+                capability: capability,
+                icao: icao,
+                source: source,
+                msg_type: 0,
+                corrected_bits: 0,
+                signal_power: None,
+                noise_power: None,
+                timestamp: None,
+                time_processed: Local::now()
+            };
+        }
+
         let msg_type = packet[4] >> 3;
 
         let msg;
@@ -33,6 +151,16 @@ impl AdsbPacket {
             msg = AdsbMsgType::AircraftID(AircraftID::new(packet[4..4+7].try_into().expect(format!("Bad aircraft id packet: {:?}", packet).as_str())));
         } else if AircraftPosition::msg_id_match(msg_type) {
             msg = AdsbMsgType::AircraftPosition(AircraftPosition::new(packet[4..4+7].try_into().expect(format!("Bad aircraft id packet: {:?}", packet).as_str())));
+        } else if SurfacePosition::msg_id_match(msg_type) {
+            msg = AdsbMsgType::SurfacePosition(SurfacePosition::new(packet[4..4+7].try_into().unwrap_or_else(|_| panic!("Bad surface position packet: {:?}", packet))));
+        } else if AirborneVelocity::msg_id_match(msg_type) {
+            msg = AdsbMsgType::AirborneVelocity(AirborneVelocity::new(packet[4..4+7].try_into().unwrap_or_else(|_| panic!("Bad aircraft velocity packet: {:?}", packet))));
+        } else if OperationalStatus::msg_id_match(msg_type) {
+            msg = AdsbMsgType::OperationalStatus(OperationalStatus::new(packet[4..4+7].try_into().unwrap_or_else(|_| panic!("Bad operational status packet: {:?}", packet))));
+        } else if AircraftStatus::msg_id_match(msg_type) {
+            msg = AdsbMsgType::AircraftStatus(AircraftStatus::new(packet[4..4+7].try_into().unwrap_or_else(|_| panic!("Bad aircraft status packet: {:?}", packet))));
+        } else if TargetStateStatus::msg_id_match(msg_type) {
+            msg = AdsbMsgType::TargetStateStatus(TargetStateStatus::new(packet[4..4+7].try_into().unwrap_or_else(|_| panic!("Bad target state packet: {:?}", packet))));
         } else {
             msg = AdsbMsgType::Uknown(UknownMsg {raw_msg: packet[4..packet.len()].to_vec()});
         }
@@ -42,8 +170,13 @@ impl AdsbPacket {
             downlink_format: downlink_format,
             capability: capability,
             icao: icao,
+            source: source,
             msg_type: msg_type,
             msg: msg,
+            corrected_bits: 0,
+            signal_power: None,
+            noise_power: None,
+            timestamp: None,
             time_processed: Local::now()
         }
     }
@@ -72,6 +205,232 @@ impl AdsbPacket {
     pub fn get_icao(&self) -> u32 {
         self.icao
     }
+
+    /// Returns the type of address identifying the target
+    pub fn get_address_type(&self) -> AddressType {
+        AddressType::from_address(self.icao)
+    }
+
+    /// Returns true if the frame is a 56 bit short reply
+    pub fn is_short(&self) -> bool {
+        self.packet.len() == 7
+    }
+
+    /// Returns true if the packet is an ADS-B extended squitter
+    pub fn is_extended_squitter(&self) -> bool {
+        AdsbPacket::is_extended_squitter_format(self.downlink_format)
+    }
+
+    /// Returns true if the downlink format carries an extended squitter ME field
+    fn is_extended_squitter_format(downlink_format: u8) -> bool {
+        downlink_format == 17 || downlink_format == 18
+    }
+
+    /// Returns true if a TIS-B or ADS-R frame flags its address as not an ICAO address
+    ///
+    /// The IMF bit is only present in position and velocity messages. It is ME bit 21 in
+    /// surface positions as ME bit 8 is part of the movement field.
+    fn is_imf_set(packet: &[u8]) -> bool {
+        match packet[4] >> 3 {
+            5..=8 => packet[6] & 0x08 != 0,
+            9..=18 | 20..=22 => packet[4] & 1 == 1,
+            19 => packet[5] >> 7 == 1,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the downlink format has the address overlaid on the parity
+    pub fn is_address_parity_format(downlink_format: u8) -> bool {
+        matches!(downlink_format, 0 | 4 | 5 | 16 | 20 | 21)
+    }
+}
+
+/// Interrogator that triggered an all call reply
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InterrogatorId {
+    /// Interrogator identifier code
+    Ii(u8),
+    /// Surveillance identifier code
+    Si(u8),
+}
+
+/// DF11 all call reply
+#[derive(Debug, Clone)]
+pub struct AllCallReply {
+    pub capability: u8,
+    pub interrogator_id: InterrogatorId,
+}
+
+impl AllCallReply {
+    /// Decode an all call reply
+    ///
+    /// `packet` - the 7 byte DF11 frame
+    ///
+    /// returns the decoded reply with the interrogator recovered from the parity overlay
+    pub fn new(packet: &[u8]) -> Self {
+        let syndrome = get_syndrome(packet);
+
+        // The low 7 bits hold the code label and the interrogator code
+        let code = (syndrome & 0xF) as u8;
+        let interrogator_id = match (syndrome >> 4) & 0x7 {
+            0 => InterrogatorId::Ii(code),
+            label => InterrogatorId::Si(code + 16 * (label as u8 - 1)),
+        };
+
+        Self {
+            capability: packet[0] & 7,
+            interrogator_id: interrogator_id,
+        }
+    }
+}
+
+impl std::fmt::Display for AllCallReply {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Message:")?;
+        writeln!(f, "Type                : All Call Reply")?;
+        writeln!(f, "Capability          : {}", self.capability)?;
+        writeln!(f, "Interrogator        : {:?}", self.interrogator_id)?;
+
+        Ok(())
+    }
+}
+
+/// Surveillance reply carrying altitude (DF0, DF4, DF16 and DF20) or identity (DF5 and DF21)
+#[derive(Debug, Clone)]
+pub struct SurveillanceReply {
+    downlink_format: u8,
+    /// Ground state from the flight or vertical status, None if it is not reported
+    pub on_ground: Option<bool>,
+    /// True if the flight status reports an alert (squawk changed or emergency)
+    pub alert: bool,
+    /// Barometric altitude in feet
+    pub altitude: Option<i32>,
+    /// Mode A code with each octal digit in 3 bits
+    pub squawk: Option<u16>,
+    /// Register inferred from the MB field of a Comm-B reply (DF20 and DF21)
+    pub comm_b: Option<CommB>,
+}
+
+impl SurveillanceReply {
+    /// Decode a surveillance reply
+    ///
+    /// `packet` - the 7 or 14 byte frame
+    ///
+    /// returns the decoded reply, the address must be checked separately
+    pub fn new(packet: &[u8]) -> Self {
+        let downlink_format = packet[0] >> 3;
+        let flight_status = packet[0] & 7;
+        // The altitude and identity codes are the 13 bits following the first 19
+        let code = ((packet[2] as u16 & 0x1F) << 8) | packet[3] as u16;
+
+        let (on_ground, alert) = match downlink_format {
+            // ACAS replies only carry the vertical status bit
+            0 | 16 => (Some(packet[0] & 4 != 0), false),
+            _ => match flight_status {
+                0 => (Some(false), false),
+                1 => (Some(true), false),
+                2 => (Some(false), true),
+                3 => (Some(true), true),
+                4 => (None, true),
+                _ => (None, false),
+            },
+        };
+
+        let (altitude, squawk) = match downlink_format {
+            5 | 21 => (None, Some(decode_id13(code))),
+            _ => (decode_ac13(code), None),
+        };
+
+        let comm_b = match downlink_format {
+            20 | 21 if packet.len() == 14 => CommB::infer(packet[4..11].try_into().expect("Bad comm-b packet")),
+            _ => None,
+        };
+
+        Self {
+            downlink_format: downlink_format,
+            on_ground: on_ground,
+            alert: alert,
+            altitude: altitude,
+            squawk: squawk,
+            comm_b: comm_b,
+        }
+    }
+}
+
+impl std::fmt::Display for SurveillanceReply {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Message:")?;
+        writeln!(f, "Type                : {} (Surveillance Reply)", self.downlink_format)?;
+        writeln!(f, "On Ground           : {}", self.on_ground.map_or_else(|| "n/a".to_string(), |g| g.to_string()))?;
+        writeln!(f, "Alert               : {}", self.alert)?;
+        writeln!(f, "Altitude (ft)       : {}", self.altitude.map_or_else(|| "n/a".to_string(), |a| a.to_string()))?;
+        writeln!(f, "Squawk              : {}", self.squawk.map_or_else(|| "n/a".to_string(), |s| format!("{:04o}", s)))?;
+        if let Some(comm_b) = &self.comm_b {
+            write!(f, "{}", comm_b)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Time an address is trusted after it was last seen in a CRC verified frame
+const ICAO_FILTER_TTL_S: i64 = 60;
+
+/// Set of recently seen ICAO addresses taken from CRC verified frames
+#[derive(Debug, Clone)]
+pub struct IcaoFilter {
+    seen: HashMap<u32, DateTime<Local>>,
+    last_expired: DateTime<Local>,
+}
+
+impl IcaoFilter {
+    pub fn new() -> Self {
+        Self {
+            seen: HashMap::new(),
+            last_expired: Local::now(),
+        }
+    }
+
+    /// Check a packet can be trusted and record its address if it was fully verified
+    ///
+    /// Extended squitters and all call replies to interrogator zero have a full CRC check,
+    /// any other all call reply or address/parity reply is only trusted if the address
+    /// recovered from it is already known. Frames repaired by error correction are trusted
+    /// but don't record their address as the repair could have changed it.
+    ///
+    /// `packet` - the packet to check
+    ///
+    /// returns true if the packet can be trusted
+    pub fn update(&mut self, packet: &AdsbPacket) -> bool {
+        let time = packet.time_processed;
+
+        let verified = match &packet.msg {
+            AdsbMsgType::AllCallReply(reply) => reply.interrogator_id == InterrogatorId::Ii(0),
+            _ => packet.is_extended_squitter(),
+        };
+
+        if verified && packet.corrected_bits == 0 {
+            self.seen.insert(packet.icao, time);
+        }
+
+        if time - self.last_expired > chrono::Duration::seconds(ICAO_FILTER_TTL_S) {
+            self.seen.retain(|_, seen| time - *seen <= chrono::Duration::seconds(ICAO_FILTER_TTL_S));
+            self.last_expired = time;
+        }
+
+        verified || self.contains(packet.icao, time)
+    }
+
+    /// Returns true if the address has been seen recently in a verified frame
+    ///
+    /// `icao` - the address to check
+    /// `time` - the current time
+    pub fn contains(&self, icao: u32, time: DateTime<Local>) -> bool {
+        match self.seen.get(&icao) {
+            Some(seen) => time - *seen <= chrono::Duration::seconds(ICAO_FILTER_TTL_S),
+            None => false,
+        }
+    }
 }
 
 impl std::fmt::Display for AdsbPacket {
@@ -88,12 +447,205 @@ impl std::fmt::Display for AdsbPacket {
         // Add the decoded metadata
         writeln!(f, "Decoded Information:")?;
         writeln!(f, "Downlink Format : {}", self.downlink_format)?;
+        writeln!(f, "Frame Length    : {} bits", if self.is_short() { 56 } else { 112 })?;
         writeln!(f, "Capability      : {}", self.capability)?;
-        writeln!(f, "ICAO            : {:06X}", self.icao)?;
+        writeln!(f, "ICAO            : {:06X}", self.icao & !NON_ICAO_ADDRESS)?;
+        writeln!(f, "Address Type    : {:?}", self.get_address_type())?;
+        writeln!(f, "Source          : {:?}", self.source)?;
         writeln!(f, "Processed Time  : {}", self.time_processed)?;
+        if let Some(timestamp) = self.timestamp {
+            writeln!(f, "Timestamp       : {}", timestamp)?;
+        }
+        if let (Some(signal_power), Some(noise_power)) = (self.signal_power, self.noise_power) {
+            writeln!(f, "Signal (dBFS)   : {:.1}", signal_power)?;
+            writeln!(f, "Noise (dBFS)    : {:.1}", noise_power)?;
+        }
         writeln!(f, "Message Type    : {}", self.msg_type)?;
         write!(f, "{}", self.msg)?;
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adsb::crc::get_adsb_crc;
+
+    #[test]
+    fn test_packet_long() {
+        let packet = AdsbPacket::_new_from_string(String::from("8D4840D6202CC371C32CE0576098"));
+
+        assert_eq!(packet.downlink_format, 17);
+        assert_eq!(packet.get_icao(), 0x4840D6);
+        assert!(!packet.is_short());
+        assert!(matches!(packet.msg, AdsbMsgType::AircraftID(_)));
+    }
+
+    /// Build a DF18 frame from a DF17 frame with the given control field
+    fn non_transponder(packet: &str, control_field: u8) -> AdsbPacket {
+        let mut packet = AdsbPacket::_new_from_string(String::from(packet)).packet;
+        packet[0] = (18 << 3) | control_field;
+
+        AdsbPacket::new(packet)
+    }
+
+    #[test]
+    fn test_packet_non_transponder() {
+        let ground_vehicle = non_transponder("8D4840D6202CC371C32CE0576098", 0);
+        assert_eq!(ground_vehicle.get_icao(), 0x4840D6);
+        assert_eq!(ground_vehicle.source, DataSource::AdsbNonTransponder);
+        assert_eq!(ground_vehicle.get_address_type(), AddressType::Icao);
+        assert!(matches!(ground_vehicle.msg, AdsbMsgType::AircraftID(_)));
+
+        let anonymous = non_transponder("8D4840D6202CC371C32CE0576098", 1);
+        assert_eq!(anonymous.get_icao(), 0x4840D6 | NON_ICAO_ADDRESS);
+        assert_eq!(anonymous.get_address_type(), AddressType::NonIcao);
+    }
+
+    #[test]
+    fn test_packet_tisb() {
+        // Airborne position with the IMF bit clear and set
+        let tisb = non_transponder("8D40621D58C382D690C8AC2863A7", 2);
+        assert_eq!(tisb.source, DataSource::TisB);
+        assert_eq!(tisb.get_address_type(), AddressType::Icao);
+        assert!(matches!(tisb.msg, AdsbMsgType::AircraftPosition(_)));
+
+        let track_file = non_transponder("8D40621D59C382D690C8AC2863A7", 2);
+        assert_eq!(track_file.get_address_type(), AddressType::NonIcao);
+
+        let adsr = non_transponder("8D40621D58C382D690C8AC2863A7", 6);
+        assert_eq!(adsr.source, DataSource::AdsR);
+
+        // Surface positions keep the same address as the movement changes
+        let surface = non_transponder("8C4841753AAB238733C8CD4020B1", 2);
+        assert_eq!(surface.get_address_type(), AddressType::Icao);
+        assert!(matches!(surface.msg, AdsbMsgType::SurfacePosition(_)));
+
+        let moving = non_transponder("8C4841753BAB238733C8CD4020B1", 2);
+        assert_eq!(moving.get_icao(), 0x484175);
+
+        let surface_track_file = non_transponder("8C4841753AAB2B8733C8CD4020B1", 2);
+        assert_eq!(surface_track_file.get_icao(), 0x484175 | NON_ICAO_ADDRESS);
+
+        // Coarse TIS-B uses a different ME layout so it is not decoded
+        let coarse = non_transponder("8D40621D58C382D690C8AC2863A7", 3);
+        assert!(matches!(coarse.msg, AdsbMsgType::Uknown(_)));
+    }
+
+    /// Build a DF11 frame with the given interrogator overlay
+    fn all_call_reply(overlay: u32) -> AdsbPacket {
+        let mut packet = vec![0x5D, 0x48, 0x40, 0xD6];
+        let parity = get_adsb_crc(&packet) ^ overlay;
+        packet.extend_from_slice(&[(parity >> 16) as u8, (parity >> 8) as u8, parity as u8]);
+
+        AdsbPacket::new(packet)
+    }
+
+    #[test]
+    fn test_packet_short() {
+        let packet = all_call_reply(0);
+
+        assert_eq!(packet.downlink_format, 11);
+        assert_eq!(packet.capability, 5);
+        assert_eq!(packet.get_icao(), 0x4840D6);
+        assert!(packet.is_short());
+        assert!(!packet.is_extended_squitter());
+    }
+
+    #[test]
+    fn test_all_call_reply() {
+        let AdsbMsgType::AllCallReply(reply) = all_call_reply(0x05).msg else { panic!("Not an all call reply") };
+        assert_eq!(reply.capability, 5);
+        assert_eq!(reply.interrogator_id, InterrogatorId::Ii(5));
+
+        let AdsbMsgType::AllCallReply(reply) = all_call_reply(0x23).msg else { panic!("Not an all call reply") };
+        assert_eq!(reply.interrogator_id, InterrogatorId::Si(19));
+    }
+
+    /// Build an address/parity frame with the address overlaid on the parity
+    fn surveillance_reply(data: &[u8], icao: u32) -> AdsbPacket {
+        let mut packet = data.to_vec();
+        let parity = get_adsb_crc(&packet) ^ icao;
+        packet.extend_from_slice(&[(parity >> 16) as u8, (parity >> 8) as u8, parity as u8]);
+
+        AdsbPacket::new(packet)
+    }
+
+    #[test]
+    fn test_surveillance_reply_altitude() {
+        let packet = surveillance_reply(&[0x20, 0x00, 0x18, 0x38], 0x4840D6);
+
+        assert_eq!(packet.downlink_format, 4);
+        assert_eq!(packet.get_icao(), 0x4840D6);
+        let AdsbMsgType::SurveillanceReply(reply) = packet.msg else { panic!("Not a surveillance reply") };
+        assert_eq!(reply.altitude, Some(38000));
+        assert_eq!(reply.squawk, None);
+        assert_eq!(reply.on_ground, Some(false));
+    }
+
+    #[test]
+    fn test_surveillance_reply_identity() {
+        let packet = surveillance_reply(&[0xAA, 0x00, 0x0A, 0xAA, 0, 0, 0, 0, 0, 0, 0], 0x7C6B30);
+
+        assert_eq!(packet.downlink_format, 21);
+        assert_eq!(packet.get_icao(), 0x7C6B30);
+        let AdsbMsgType::SurveillanceReply(reply) = packet.msg else { panic!("Not a surveillance reply") };
+        assert_eq!(reply.squawk, Some(0o7700));
+        assert_eq!(reply.altitude, None);
+        assert!(reply.alert);
+    }
+
+    #[test]
+    fn test_icao_filter() {
+        let mut filter = IcaoFilter::new();
+        let unverified = all_call_reply(0x05);
+        let time = unverified.time_processed;
+
+        assert!(!filter.update(&unverified));
+        assert!(!filter.contains(0x4840D6, time));
+
+        assert!(filter.update(&all_call_reply(0)));
+        assert!(filter.contains(0x4840D6, time));
+        assert!(filter.update(&unverified));
+        assert!(!filter.contains(0x4840D6, time + chrono::Duration::seconds(ICAO_FILTER_TTL_S + 1)));
+
+        let squitter = AdsbPacket::_new_from_string(String::from("8D7C6B3020293532D70820FC8090"));
+        assert!(filter.update(&squitter));
+        assert!(filter.contains(0x7C6B30, time));
+    }
+
+    #[test]
+    fn test_icao_filter_address_parity() {
+        let mut filter = IcaoFilter::new();
+        let reply = surveillance_reply(&[0x20, 0x00, 0x18, 0x38], 0x7C6B30);
+
+        assert!(!filter.update(&reply));
+        assert!(!filter.contains(0x7C6B30, reply.time_processed));
+
+        let squitter = AdsbPacket::_new_from_string(String::from("8D7C6B3020293532D70820FC8090"));
+        assert!(filter.update(&squitter));
+        assert!(filter.update(&reply));
+
+        // A bit error recovers a different address which is not known
+        let corrupted = surveillance_reply(&[0x20, 0x00, 0x18, 0x38], 0x7C6B31);
+        assert!(!filter.update(&corrupted));
+    }
+
+    #[test]
+    fn test_icao_filter_corrected() {
+        let mut filter = IcaoFilter::new();
+        let mut repaired = AdsbPacket::_new_from_string(String::from("8D4840D6202CC371C32CE0576098"));
+        repaired.corrected_bits = 1;
+        let time = repaired.time_processed;
+
+        // A repaired squitter is passed on but doesn't vouch for its address
+        assert!(filter.update(&repaired));
+        assert!(!filter.contains(0x4840D6, time));
+        assert!(!filter.update(&all_call_reply(0x05)));
+
+        let squitter = AdsbPacket::_new_from_string(String::from("8D4840D6202CC371C32CE0576098"));
+        assert!(filter.update(&squitter));
+        assert!(filter.contains(0x4840D6, time));
+    }
+}
@@ -0,0 +1,449 @@
+/// Spread demodulation of the sample stream across a pool of worker threads
+///
+/// Each buffer is handed to a worker along with the end of the buffer before it so frames
+/// crossing a buffer boundary are still found. Workers skip the samples of every frame they find
+/// as if it will be used. The frames are merged back in sample order, any frame overlapping one
+/// already used is dropped and the samples skipped for frames that weren't used are searched
+/// again, the same as the single threaded scan.
+///
+/// Author: Jack Duignan (JackpDuignan@gmail.com)
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::adsb::demod::{DemodFrame, DemodStats, Demodulator, FrameAction, SampleBuffer};
+use crate::cli::ErrorCorrection;
+use crate::utils::Sample;
+
+/// Number of chunks that can be queued for each worker before the sdr data is held back
+const QUEUED_CHUNKS_PER_WORKER: usize = 2;
+
+/// A buffer of samples for a worker to demodulate
+struct Chunk<T> {
+    /// Position of the chunk in the stream
+    sequence: u64,
+    /// Index of the first sample in the stream
+    first_sample: u64,
+    samples: Vec<T>,
+}
+
+/// The frames found in a chunk
+struct ChunkResult {
+    sequence: u64,
+    /// Index of the first sample in the stream
+    first_sample: u64,
+    /// The magnitudes of the chunk kept to search the samples of frames that aren't used
+    mags: Vec<u32>,
+    /// The frames and the index of their first sample in the chunk
+    frames: Vec<(usize, DemodFrame)>,
+    /// The samples in the chunk where a preamble was found
+    preambles: Vec<usize>,
+}
+
+/// Find the frames in a buffer skipping the samples of each frame found
+///
+/// `demodulator` - the demodulator to use
+/// `mags` - the magnitude buffer
+/// `correction` - the error correction policy
+///
+/// returns the frames and the sample they start at, and the samples a preamble was found at
+fn find_frames(demodulator: &dyn Demodulator, mags: &[u32], correction: ErrorCorrection) -> (Vec<(usize, DemodFrame)>, Vec<usize>) {
+    let window = demodulator.get_window();
+    let mut stats = DemodStats::default();
+    let mut frames = Vec::new();
+    let mut preambles = Vec::new();
+
+    let mut i = 0;
+    while i + window <= mags.len() {
+        let found = demodulator.demodulate(mags, i, correction, &mut stats);
+        if stats.preambles as usize > preambles.len() {
+            preambles.push(i);
+        }
+
+        match found {
+            Some(frame) => {
+                i += frame.length;
+                frames.push((i - frame.length, frame));
+            },
+            None => i += 1,
+        }
+    }
+
+    (frames, preambles)
+}
+
+/// Offer the frames found in a chunk to the consumer in sample order
+///
+/// The samples a worker skipped for a frame that isn't used are searched again here so the
+/// frame doesn't hide the frames it overlaps. Preambles the worker found inside a frame that
+/// was used aren't counted as the single threaded scan never searches them.
+///
+/// `demodulator` - the demodulator to search the skipped samples with
+/// `chunk` - the frames and magnitudes of the chunk
+/// `correction` - the error correction policy
+/// `last_end` - the sample following the last frame used
+/// `stats` - the statistics to update
+/// `accept` - the consumer of the frames
+///
+/// returns false if the consumer stopped the pipeline
+fn merge_chunk<F>(demodulator: &dyn Demodulator, chunk: ChunkResult, correction: ErrorCorrection, last_end: &mut u64, stats: &mut DemodStats, accept: &mut F) -> bool
+where
+    F: FnMut(u64, DemodFrame) -> FrameAction,
+{
+    // Only the starts that leave room for a whole frame belong to this chunk
+    let starts = (chunk.mags.len() + 1).saturating_sub(demodulator.get_window());
+    let mut frames = chunk.frames.into_iter().peekable();
+    // The end of the samples the worker skipped for frames that weren't used
+    let mut skipped_end = 0;
+    let mut i = last_end.saturating_sub(chunk.first_sample) as usize;
+    // The samples inside used frames, the first is the end of a frame from an earlier chunk
+    let mut used = vec![(0, i)];
+
+    loop {
+        while let Some((start, frame)) = frames.next_if(|(start, _)| *start < i) {
+            skipped_end = skipped_end.max(start + frame.length);
+        }
+
+        let searched = i >= skipped_end;
+        let (start, frame) = if searched {
+            match frames.next() {
+                Some(found) => found,
+                None => break,
+            }
+        } else {
+            if i >= starts {
+                break;
+            }
+            match demodulator.demodulate(&chunk.mags, i, correction, stats) {
+                Some(frame) => (i, frame),
+                None => {
+                    i += 1;
+                    continue;
+                },
+            }
+        };
+
+        let (length, corrected_bits) = (frame.length, frame.corrected_bits);
+        match accept(chunk.first_sample + start as u64, frame) {
+            FrameAction::Use => {
+                stats.add_good(corrected_bits);
+                used.push((start + 1, start + length));
+                i = start + length;
+                *last_end = chunk.first_sample + i as u64;
+            },
+            FrameAction::Reject => {
+                // The worker skipped the samples of its frames
+                if searched {
+                    skipped_end = start + length;
+                }
+                i = start + 1;
+            },
+            FrameAction::Stop => return false,
+        }
+    }
+
+    let mut used = used.into_iter().peekable();
+    for preamble in chunk.preambles {
+        while used.next_if(|(_, end)| *end <= preamble).is_some() {}
+        if used.peek().is_none_or(|(first, _)| preamble < *first) {
+            stats.preambles += 1;
+        }
+    }
+
+    true
+}
+
+/// Demodulate chunks until the queue is closed
+fn worker_thread<T: Sample>(jobs: Arc<Mutex<Receiver<Chunk<T>>>>, results: Sender<ChunkResult>, demodulator: Arc<dyn Demodulator + Send + Sync>, correction: ErrorCorrection) {
+    loop {
+        let chunk = match jobs.lock().expect("Demod job queue poisoned").recv() {
+            Ok(chunk) => chunk,
+            Err(_) => return,
+        };
+
+        let mags = T::get_magnitude(&chunk.samples);
+        let (frames, preambles) = find_frames(demodulator.as_ref(), &mags, correction);
+
+        let result = ChunkResult {
+            sequence: chunk.sequence,
+            first_sample: chunk.first_sample,
+            mags: mags,
+            frames: frames,
+            preambles: preambles,
+        };
+
+        if results.send(result).is_err() {
+            return;
+        }
+    }
+}
+
+/// Split the incoming sample buffers into overlapping chunks for the workers
+///
+/// `overlap` - the number of samples from the end of each buffer repeated at the start of the next
+fn dispatch_thread<T: Sample>(rx: Receiver<SampleBuffer<T>>, jobs: SyncSender<Chunk<T>>, overlap: usize) {
+    let mut tail: Vec<T> = Vec::new();
+    let mut sequence = 0;
+    let mut next_sample = 0;
+
+    while let Ok(buf) = rx.recv() {
+        // Samples lost between buffers can't be joined
+        if buf.first_sample != next_sample {
+            tail.clear();
+        }
+
+        let first_sample = buf.first_sample - tail.len() as u64;
+        next_sample = buf.first_sample + buf.samples.len() as u64;
+
+        let mut samples = std::mem::take(&mut tail);
+        samples.extend_from_slice(&buf.samples);
+        tail = samples[samples.len().saturating_sub(overlap)..].to_vec();
+
+        let chunk = Chunk {
+            sequence: sequence,
+            first_sample: first_sample,
+            samples: samples,
+        };
+        sequence += 1;
+
+        if jobs.send(chunk).is_err() {
+            return;
+        }
+    }
+}
+
+/// Demodulate a stream of sample buffers across a pool of workers
+///
+/// `rx` - the incoming sample buffers
+/// `demodulator` - the demodulator shared by the workers
+/// `correction` - the error correction policy
+/// `workers` - the number of worker threads
+/// `shared_stats` - updated with the statistics so far as each chunk is merged
+/// `accept` - called with the index of the first sample and each frame in sample order,
+/// returns what was done with the frame, the frames overlapping a used frame are dropped
+///
+/// returns the statistics of the stream up to when it ended or `accept` stopped it
+pub fn process_parallel<T, F>(rx: Receiver<SampleBuffer<T>>, demodulator: Arc<dyn Demodulator + Send + Sync>, correction: ErrorCorrection, workers: usize, shared_stats: &Mutex<DemodStats>, mut accept: F) -> DemodStats
+where
+    T: Sample,
+    F: FnMut(u64, DemodFrame) -> FrameAction,
+{
+    // Every start sample is searched by exactly one chunk
+    let overlap = demodulator.get_window() - 1;
+    let (jobs_tx, jobs_rx) = mpsc::sync_channel(workers * QUEUED_CHUNKS_PER_WORKER);
+    let jobs_rx = Arc::new(Mutex::new(jobs_rx));
+    let (results_tx, results_rx) = mpsc::channel();
+
+    for _ in 0..workers {
+        let jobs = Arc::clone(&jobs_rx);
+        let results = results_tx.clone();
+        let demodulator = Arc::clone(&demodulator);
+        thread::spawn(move || worker_thread(jobs, results, demodulator, correction));
+    }
+    drop(results_tx);
+
+    thread::spawn(move || dispatch_thread(rx, jobs_tx, overlap));
+
+    let mut stats = DemodStats::default();
+    let mut pending: BTreeMap<u64, ChunkResult> = BTreeMap::new();
+    let mut next_sequence = 0;
+    let mut last_end = 0;
+
+    while let Ok(result) = results_rx.recv() {
+        pending.insert(result.sequence, result);
+
+        while let Some(result) = pending.remove(&next_sequence) {
+            next_sequence += 1;
+
+            // Dropping the results stops the workers and then the dispatcher
+            if !merge_chunk(demodulator.as_ref(), result, correction, &mut last_end, &mut stats, &mut accept) {
+                *shared_stats.lock().expect("Demod stats poisoned") = stats.clone();
+                return stats;
+            }
+        }
+        *shared_stats.lock().expect("Demod stats poisoned") = stats.clone();
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adsb::crc::get_adsb_crc;
+    use crate::adsb::demod::{StreamDemod, TwoSampleDemod};
+    use crate::adsb::fixtures::{to_samples, PREAMBLE};
+    use crate::utils::get_magnitude;
+    use num_complex::Complex;
+
+    /// Build an extended squitter for an address with a valid parity
+    fn get_frame(icao: u32) -> Vec<u8> {
+        let mut frame = vec![0x8D, (icao >> 16) as u8, (icao >> 8) as u8, icao as u8, 0x20, 0x15, 0xA6, 0x78, 0xD4, 0xD2, 0x20];
+        let crc = get_adsb_crc(&frame);
+        frame.extend_from_slice(&[(crc >> 16) as u8, (crc >> 8) as u8, crc as u8]);
+
+        frame
+    }
+
+    /// Build a sample stream with the frames at the given samples
+    fn get_stream(frames: &[(usize, Vec<u8>)], len: usize) -> Vec<Complex<i16>> {
+        // A rising ramp never looks like a preamble
+        let mut mags: Vec<i16> = (0..len).map(|i| (i % 7) as i16).collect();
+
+        for (start, frame) in frames {
+            let samples = PREAMBLE.iter().copied().chain(to_samples(frame));
+            for (mag, sample) in mags[*start..].iter_mut().zip(samples) {
+                *mag = sample as i16;
+            }
+        }
+
+        mags.iter().map(|mag| Complex::new(*mag, 0)).collect()
+    }
+
+    /// Keep only extended squitters
+    fn is_extended_squitter(frame: &DemodFrame) -> bool {
+        frame.packet[0] >> 3 == 17
+    }
+
+    /// Split a stream into buffers of the given size
+    fn get_buffers(stream: &[Complex<i16>], size: usize) -> Vec<SampleBuffer> {
+        stream.chunks(size)
+            .enumerate()
+            .map(|(i, samples)| SampleBuffer { first_sample: (i * size) as u64, samples: samples.to_vec() })
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let frames: Vec<(usize, Vec<u8>)> = (0..40)
+            .map(|i| (100 + i * 613, get_frame(0x400000 + i as u32)))
+            .collect();
+        let stream = get_stream(&frames, 26_000);
+        let buffers = get_buffers(&stream, 1000);
+
+        let mut sequential = Vec::new();
+        let mut stats = DemodStats::default();
+        let mut demod = StreamDemod::new(Box::new(TwoSampleDemod));
+        for buf in buffers.iter() {
+            demod.process(buf.first_sample, &get_magnitude(&buf.samples), ErrorCorrection::OneBit, &mut stats, |sample, frame| {
+                if !is_extended_squitter(&frame) {
+                    return FrameAction::Reject;
+                }
+                sequential.push((sample as usize, frame.packet));
+                FrameAction::Use
+            });
+        }
+
+        for workers in [1, 2, 4] {
+            let (tx, rx) = mpsc::channel();
+            for buf in buffers.iter() {
+                tx.send(buf.clone()).unwrap();
+            }
+            drop(tx);
+
+            let mut parallel = Vec::new();
+            let parallel_stats = process_parallel(rx, Arc::new(TwoSampleDemod), ErrorCorrection::OneBit, workers, &Mutex::default(), |sample, frame| {
+                if !is_extended_squitter(&frame) {
+                    return FrameAction::Reject;
+                }
+                parallel.push((sample as usize, frame.packet));
+                FrameAction::Use
+            });
+
+            assert_eq!(parallel, sequential);
+            assert_eq!(parallel_stats.good, 40);
+            // The samples of the frames aren't searched again
+            assert_eq!(parallel_stats.preambles, stats.preambles);
+        }
+
+        // Frames crossing a buffer boundary are found once in order at the right sample
+        assert_eq!(sequential, frames);
+    }
+
+    /// Reports a one sample frame of the given length wherever the magnitude is marked
+    struct MarkerDemod;
+
+    impl Demodulator for MarkerDemod {
+        fn get_window(&self) -> usize {
+            10
+        }
+
+        fn demodulate(&self, mags: &[u32], start: usize, _correction: ErrorCorrection, stats: &mut DemodStats) -> Option<DemodFrame> {
+            if mags[start] < 100 {
+                return None;
+            }
+            stats.preambles += 1;
+
+            Some(DemodFrame {
+                packet: vec![mags[start] as u8],
+                corrected_bits: 0,
+                signal_power: 0.0,
+                noise_power: 0.0,
+                length: 8,
+            })
+        }
+    }
+
+    #[test]
+    fn test_overlapping_frames_dropped() {
+        let mut stream = vec![Complex::new(0i16, 0i16); 100];
+        // The frame at 21 overlaps the one at 20, the one at 28 starts after it ends
+        for (i, mag) in [(20, 100), (21, 101), (28, 102), (45, 103), (47, 104), (60, 105)] {
+            stream[i] = Complex::new(mag, 0);
+        }
+
+        let accept = |frame: &DemodFrame| match frame.packet[0] {
+            103 => FrameAction::Reject,
+            _ => FrameAction::Use,
+        };
+        let mut stats = DemodStats::default();
+        StreamDemod::new(Box::new(MarkerDemod)).process(0, &get_magnitude(&stream), ErrorCorrection::Off, &mut stats, |_, frame| accept(&frame));
+
+        for split in [22, 25, 46, 50] {
+            let (tx, rx) = mpsc::channel();
+            tx.send(SampleBuffer { first_sample: 0, samples: stream[0..split].to_vec() }).unwrap();
+            tx.send(SampleBuffer { first_sample: split as u64, samples: stream[split..].to_vec() }).unwrap();
+            drop(tx);
+
+            let mut found = Vec::new();
+            let parallel_stats = process_parallel(rx, Arc::new(MarkerDemod), ErrorCorrection::Off, 3, &Mutex::default(), |sample, frame| {
+                assert_eq!(stream[sample as usize].re as u8, frame.packet[0]);
+                found.push(frame.packet[0]);
+                // A rejected frame doesn't hide the frames it overlaps
+                accept(&frame)
+            });
+
+            assert_eq!(found, vec![100, 102, 103, 104, 105], "split at {}", split);
+            // The frame at 21 inside the used frame at 20 isn't counted
+            assert_eq!(parallel_stats.preambles, stats.preambles, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn test_parallel_stop() {
+        let mut stream = vec![Complex::new(0i16, 0i16); 100];
+        for (i, mag) in [(20, 100), (40, 101), (60, 102)] {
+            stream[i] = Complex::new(mag, 0);
+        }
+
+        // The sender is kept open like a live sdr so only stopping ends the pipeline
+        let (tx, rx) = mpsc::channel();
+        for buf in get_buffers(&stream, 30) {
+            tx.send(buf).unwrap();
+        }
+
+        let mut found = Vec::new();
+        process_parallel(rx, Arc::new(MarkerDemod), ErrorCorrection::Off, 2, &Mutex::default(), |_, frame| {
+            found.push(frame.packet[0]);
+            match frame.packet[0] {
+                101 => FrameAction::Stop,
+                _ => FrameAction::Use,
+            }
+        });
+
+        assert_eq!(found, vec![100, 101]);
+        drop(tx);
+    }
+}
@@ -0,0 +1,311 @@
+/// Track the aircraft in range of the receiver and report when they appear and disappear
+///
+/// The displays and any logger subscribe to the lifecycle events instead of comparing
+/// aircraft state themselves.
+///
+/// Author: Jack Duignan (JackpDuignan@gmail.com)
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Instant;
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::adsb::aircraft::{Aircraft, ReceiverConfig};
+use crate::adsb::cpr::GeographicPosition;
+use crate::adsb::packet::{format_address, AdsbPacket};
+
+/// Changes in the lifecycle of a tracked aircraft
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+#[serde(tag = "type")]
+pub enum TrackerEvent {
+    /// The first packet was received from an aircraft
+    NewAircraft { icao: u32 },
+    /// The first position of an aircraft was decoded
+    PositionAcquired { icao: u32, position: GeographicPosition },
+    /// An aircraft broadcast a new callsign
+    CallsignChanged { icao: u32, previous: Option<String>, callsign: String },
+    /// An aircraft was removed after it stopped transmitting
+    Lost { icao: u32 },
+}
+
+impl std::fmt::Display for TrackerEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrackerEvent::NewAircraft { icao } => write!(f, "New aircraft {}", format_address(*icao)),
+            TrackerEvent::PositionAcquired { icao, position } => {
+                write!(f, "Position acquired {} {:.6} {:.6}", format_address(*icao), position.latitude, position.longitude)
+            },
+            TrackerEvent::CallsignChanged { icao, previous: Some(previous), callsign } => {
+                write!(f, "Callsign changed {} {} -> {}", format_address(*icao), previous, callsign)
+            },
+            TrackerEvent::CallsignChanged { icao, previous: None, callsign } => write!(f, "Callsign {} {}", format_address(*icao), callsign),
+            TrackerEvent::Lost { icao } => write!(f, "Lost aircraft {}", format_address(*icao)),
+        }
+    }
+}
+
+/// The aircraft currently being received
+#[derive(Debug)]
+pub struct AircraftTracker {
+    aircrafts: HashMap<u32, Aircraft>,
+    /// Receiver settings given to new aircraft
+    config: ReceiverConfig,
+    /// Aircraft silent for longer than this are removed
+    timeout: chrono::Duration,
+    /// Sample clock time of the newest packet and when it arrived, silence is measured on the
+    /// sample clock so drift from the wall clock or playback speed doesn't expire aircraft
+    latest: Option<(DateTime<Local>, Instant)>,
+    subscribers: Vec<Sender<TrackerEvent>>,
+}
+
+impl AircraftTracker {
+    /// Create an empty tracker
+    ///
+    /// `config` - the receiver settings used for new aircraft
+    /// `timeout_s` - seconds without a packet before an aircraft is removed
+    pub fn new(config: ReceiverConfig, timeout_s: i64) -> Self {
+        AircraftTracker {
+            aircrafts: HashMap::new(),
+            config: config,
+            timeout: chrono::Duration::seconds(timeout_s),
+            latest: None,
+            subscribers: Vec::new(),
+        }
+    }
+
+    /// Subscribe to the lifecycle events of the tracked aircraft
+    ///
+    /// returns the receiver the events are sent on, dropping it ends the subscription
+    pub fn subscribe(&mut self) -> Receiver<TrackerEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.push(tx);
+
+        rx
+    }
+
+    /// Send an event to every subscriber forgetting any that have gone
+    fn publish(&mut self, event: TrackerEvent) {
+        self.subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    /// Update the aircraft a packet is from adding it if it is new
+    ///
+    /// `packet` - the packet to handle
+    ///
+    /// returns the updated aircraft
+    pub fn handle_packet(&mut self, packet: AdsbPacket) -> &Aircraft {
+        let icao = packet.get_icao();
+        let time = packet.time_processed;
+        if self.latest.is_none_or(|(latest, _)| time >= latest) {
+            self.latest = Some((time, Instant::now()));
+        }
+
+        let mut events = Vec::new();
+        let config = &self.config;
+        let aircraft = self.aircrafts.entry(icao).or_insert_with(|| {
+            events.push(TrackerEvent::NewAircraft { icao });
+            Aircraft::with_config(icao, config.clone())
+        });
+
+        let had_position = aircraft.get_geo_position().is_some();
+        let previous_callsign = aircraft.get_callsign();
+
+        aircraft.handle_packet(packet);
+
+        if let (false, Some(position)) = (had_position, aircraft.get_geo_position()) {
+            events.push(TrackerEvent::PositionAcquired { icao, position });
+        }
+
+        let callsign = aircraft.get_callsign();
+        if !callsign.is_empty() && callsign != previous_callsign {
+            let previous = Some(previous_callsign).filter(|previous| !previous.is_empty());
+            events.push(TrackerEvent::CallsignChanged { icao, previous, callsign });
+        }
+
+        for event in events {
+            self.publish(event);
+        }
+
+        &self.aircrafts[&icao]
+    }
+
+    /// Get the current time on the sample clock
+    ///
+    /// The current sample clock time is the time of the newest packet plus the time since it
+    /// arrived.
+    ///
+    /// `now` - the current instant
+    ///
+    /// returns the sample clock time or None if no packets have been received
+    pub fn get_time(&self, now: Instant) -> Option<DateTime<Local>> {
+        let (latest, arrived) = self.latest?;
+        let elapsed = chrono::Duration::from_std(now.saturating_duration_since(arrived)).unwrap_or(chrono::Duration::MAX);
+
+        Some(latest + elapsed)
+    }
+
+    /// Remove the aircraft that have stopped transmitting
+    ///
+    /// `now` - the current instant
+    pub fn prune(&mut self, now: Instant) {
+        let Some(now) = self.get_time(now) else {
+            return;
+        };
+        let timeout = self.timeout;
+
+        let mut lost: Vec<u32> = self.aircrafts.values()
+            .filter(|aircraft| now - aircraft.get_last_contact() > timeout)
+            .map(|aircraft| aircraft.get_icao())
+            .collect();
+        lost.sort();
+
+        for icao in lost {
+            self.aircrafts.remove(&icao);
+            self.publish(TrackerEvent::Lost { icao });
+        }
+    }
+
+    /// Returns the tracked aircraft in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = &Aircraft> {
+        self.aircrafts.values()
+    }
+
+    /// Returns the tracked aircraft with the given address
+    pub fn get(&self, icao: u32) -> Option<&Aircraft> {
+        self.aircrafts.get(&icao)
+    }
+
+    /// Returns the number of tracked aircraft
+    pub fn len(&self) -> usize {
+        self.aircrafts.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::time::Duration;
+    use crate::adsb::packet::{AddressType, DataSource};
+
+    fn get_packet(packet: &str, time: DateTime<Local>) -> AdsbPacket {
+        let mut packet = AdsbPacket::_new_from_string(String::from_str(packet).unwrap());
+        packet.time_processed = time;
+
+        packet
+    }
+
+    #[test]
+    fn test_tracker_lifecycle_events() {
+        let mut tracker = AircraftTracker::new(ReceiverConfig::default(), 60);
+        let events = tracker.subscribe();
+        let start = Local::now();
+
+        tracker.handle_packet(get_packet("8d7c6b3020293532d70820fc8090", start));
+        tracker.handle_packet(get_packet("8d7c6b30580d107903b3cabf62ab", start));
+        tracker.handle_packet(get_packet("8d7c6b30580d24eeaebb2dfea5bb", start + chrono::Duration::seconds(1)));
+        // Repeats don't raise events again
+        tracker.handle_packet(get_packet("8d7c6b3020293532d70820fc8090", start + chrono::Duration::seconds(2)));
+        tracker.handle_packet(get_packet("8d7c6b30580d24eeaebb2dfea5bb", start + chrono::Duration::seconds(3)));
+
+        let events: Vec<TrackerEvent> = events.try_iter().collect();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], TrackerEvent::NewAircraft { icao: 0x7C6B30 }));
+        assert!(matches!(&events[1], TrackerEvent::CallsignChanged { icao: 0x7C6B30, previous: None, callsign } if callsign == "JST250__"));
+        assert!(matches!(&events[2], TrackerEvent::PositionAcquired { icao: 0x7C6B30, position } if (position.latitude - -41.28964698920816).abs() < 0.0001));
+        assert_eq!(tracker.len(), 1);
+    }
+
+    #[test]
+    fn test_tracker_callsign_changed() {
+        let mut tracker = AircraftTracker::new(ReceiverConfig::default(), 60);
+        let events = tracker.subscribe();
+        let start = Local::now();
+
+        // Comm-B identification followed by a different ADS-B callsign
+        tracker.handle_packet(get_packet("A8000AAA202CC371C32CE04612F0", start));
+        tracker.handle_packet(get_packet("8d7c6b3020293532d70820fc8090", start));
+
+        let events: Vec<TrackerEvent> = events.try_iter().collect();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(&events[1], TrackerEvent::CallsignChanged { previous: None, callsign, .. } if callsign == "KLM1023_"));
+        assert!(matches!(&events[2], TrackerEvent::CallsignChanged { icao: 0x7C6B30, previous: Some(previous), callsign }
+            if previous == "KLM1023_" && callsign == "JST250__"));
+    }
+
+    #[test]
+    fn test_tracker_prune() {
+        let mut tracker = AircraftTracker::new(ReceiverConfig::default(), 60);
+        let events = tracker.subscribe();
+        let start = Local::now() - chrono::Duration::seconds(300);
+
+        tracker.handle_packet(get_packet("8d7c6b30581304f388bb4455896f", start));
+        tracker.handle_packet(get_packet("8D485020994409940838175B284F", start + chrono::Duration::seconds(30)));
+
+        // 59 seconds after the first packet on the sample clock
+        tracker.prune(Instant::now() + Duration::from_secs(29));
+        assert_eq!(tracker.len(), 2);
+
+        // Ages are also measured on the sample clock
+        let now = tracker.get_time(Instant::now() + Duration::from_secs(29)).unwrap();
+        assert_eq!(tracker.get(0x7C6B30).unwrap().get_age(now), 59);
+
+        tracker.prune(Instant::now() + Duration::from_secs(32));
+        assert_eq!(tracker.len(), 1);
+        assert!(tracker.get(0x485020).is_some());
+
+        // The packet times are far behind the wall clock but silence is measured on the sample clock
+        tracker.handle_packet(get_packet("8D485020994409940838175B284F", start + chrono::Duration::seconds(200)));
+        tracker.prune(Instant::now());
+        assert_eq!(tracker.len(), 1);
+
+        tracker.prune(Instant::now() + Duration::from_secs(62));
+        assert_eq!(tracker.len(), 0);
+
+        let lost: Vec<u32> = events.try_iter()
+            .filter_map(|event| match event {
+                TrackerEvent::Lost { icao } => Some(icao),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lost, vec![0x7C6B30, 0x485020]);
+    }
+
+    #[test]
+    fn test_tracker_dropped_subscriber() {
+        let mut tracker = AircraftTracker::new(ReceiverConfig::default(), 60);
+        let dropped = tracker.subscribe();
+        let events = tracker.subscribe();
+        drop(dropped);
+
+        tracker.handle_packet(get_packet("8d7c6b30581304f388bb4455896f", Local::now()));
+
+        assert_eq!(tracker.subscribers.len(), 1);
+        assert_eq!(events.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn test_tracker_non_icao() {
+        let mut tracker = AircraftTracker::new(ReceiverConfig::default(), 60);
+        let events = tracker.subscribe();
+        let adsb = AdsbPacket::_new_from_string(String::from_str("8D4840D6202CC371C32CE0576098").unwrap());
+        // The same address from an anonymous DF18 transmitter
+        let anonymous = AdsbPacket::_new_from_string(String::from_str("914840D6202CC371C32CE0576098").unwrap());
+
+        tracker.handle_packet(adsb);
+        let aircraft = tracker.handle_packet(anonymous);
+
+        assert_eq!(aircraft.get_address_type(), AddressType::NonIcao);
+        assert_eq!(aircraft.get_source(), DataSource::AdsbNonTransponder);
+        assert_eq!(aircraft.get_address_string(), "~4840d6");
+        assert_eq!(tracker.len(), 2);
+        assert_eq!(tracker.get(0x4840D6).unwrap().get_source(), DataSource::Adsb);
+
+        let printed: Vec<String> = events.try_iter().map(|event| event.to_string()).collect();
+        assert_eq!(printed, ["New aircraft 4840d6", "Callsign 4840d6 KLM1023_", "New aircraft ~4840d6", "Callsign ~4840d6 KLM1023_"]);
+    }
+}
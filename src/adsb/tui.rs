@@ -5,30 +5,45 @@
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::{
     layout::{Constraint, Direction, Layout},
-    style::{Stylize}, text::Line, widgets::{Block, Cell, Row, Table}, DefaultTerminal, Frame
+    style::{Style, Stylize}, text::Line, widgets::{Block, Cell, Paragraph, Row, Table}, DefaultTerminal, Frame
 };
 
-use std::{collections::{hash_map, HashMap}, error::Error, sync::mpsc::Receiver};
-use std::time::Duration;
+use std::{error::Error, sync::mpsc::Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::Local;
 
 use crate::adsb::{msgs::AircraftPosition, packet::AdsbPacket};
-use crate::adsb::aircraft::{Aircraft, handle_aircraft_update};
+use crate::adsb::aircraft::Aircraft;
+use crate::adsb::demod::DemodStats;
+use crate::adsb::tracker::{AircraftTracker, TrackerEvent};
 
 /// The main application which holds the state and logic of the application.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 struct App {
     /// Is the application running?
     running: bool,
-    aircrafts: hash_map::HashMap<u32, Aircraft>,
+    tracker: AircraftTracker,
+    events: Receiver<TrackerEvent>,
+    /// The most recent lifecycle event shown in the footer
+    last_event: Option<TrackerEvent>,
     num_packets: u32,
+    /// The demodulator statistics shown on the footer border
+    stats: Arc<Mutex<DemodStats>>,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(mut tracker: AircraftTracker, stats: Arc<Mutex<DemodStats>>) -> Self {
+        let events = tracker.subscribe();
+
         App {
             running: false,
-            aircrafts: HashMap::new(),
+            tracker: tracker,
+            events: events,
+            last_event: None,
             num_packets: 0,
+            stats: stats,
         }
     }
 
@@ -39,7 +54,12 @@ impl App {
         while self.running {
             while let Ok(packet) = rx.try_recv() {
                 self.num_packets += 1;
-                handle_aircraft_update(packet, &mut self.aircrafts);
+                self.tracker.handle_packet(packet);
+            }
+            self.tracker.prune(Instant::now());
+
+            while let Ok(event) = self.events.try_recv() {
+                self.last_event = Some(event);
             }
             terminal.draw(|frame| self.render(frame))?;
             self.handle_crossterm_events()?;
@@ -54,7 +74,7 @@ impl App {
             .margin(1)
             .constraints([
                 Constraint::Min(0),
-                // Constraint::Length(3),
+                Constraint::Length(3),
             ])
             .split(frame.area());
 
@@ -64,39 +84,62 @@ impl App {
             .light_magenta()
             .centered();
         
-        let binding = self.aircrafts.clone();
-        let mut sorted_aircrafts: Vec<&Aircraft> = binding.values().collect();
-        sorted_aircrafts.sort_by(|a, b| a.get_age().cmp(&b.get_age()));
+        let now = self.tracker.get_time(Instant::now()).unwrap_or_else(Local::now);
+        let mut sorted_aircrafts: Vec<&Aircraft> = self.tracker.iter().collect();
+        sorted_aircrafts.sort_by(|a, b| a.get_age(now).cmp(&b.get_age(now)));
 
         let rows = sorted_aircrafts.iter().map(|plane| {
             let pos = plane.get_geo_position();
-            Row::new(vec![
-                Cell::from(format!("{:x}", plane.get_icao())),
+            let squawk = plane.get_squawk().unwrap_or_else(|| "n/a".to_string());
+            let squawk = match plane.get_emergency() {
+                Some(emergency) if plane.is_emergency() => format!("{} {}", squawk, emergency),
+                _ => squawk,
+            };
+
+            let row = Row::new(vec![
+                Cell::from(plane.get_address_string()),
                 Cell::from(format!("{}", plane.get_callsign())),
-                Cell::from(format!("{}", plane.get_altitude_ft())),
+                Cell::from(squawk),
+                Cell::from(if plane.is_on_ground() { "ground".to_string() } else { plane.get_altitude_ft().map_or_else(|| "n/a".to_string(), |a| format!("{}", a)) }),
                 Cell::from(pos.clone().map_or_else(|| "n/a".to_string(), |p| format!("{:.6}", p.latitude))),
                 Cell::from(pos.map_or_else(|| "n/a".to_string(), |p| format!("{:.6}", p.longitude))),
-                Cell::from("n/a"),
-                Cell::from(format!("{}", plane.get_age())),
-            ])
+                Cell::from(plane.get_ground_speed().map_or_else(|| "n/a".to_string(), |v| format!("{:.0}", v))),
+                Cell::from(plane.get_signal_power().map_or_else(|| "n/a".to_string(), |s| format!("{:.1}", s))),
+                Cell::from(format!("{}", plane.get_age(now))),
+            ]);
+
+            if plane.is_emergency() {
+                row.style(Style::new().red().bold())
+            } else {
+                row
+            }
         });
 
         let column_widths = [
-            Constraint::Length(6),
+            Constraint::Length(7),
             Constraint::Length(10),
+            Constraint::Length(14),
             Constraint::Length(10),
             Constraint::Length(10),
             Constraint::Length(10),
             Constraint::Length(10),
+            Constraint::Length(6),
             Constraint::Length(5),
         ];
 
         let table = Table::new(rows, column_widths)
-            .header(Row::new(vec!["ICAO", "Callsign", "Altitude", "Latitude", "Longitude", "Velocity", "Age"]).bold())
+            .header(Row::new(vec!["ICAO", "Callsign", "Squawk", "Altitude", "Latitude", "Longitude", "Velocity", "RSSI", "Age"]).bold())
             .block(Block::bordered().title(title));
 
+        let stats = self.stats.lock().expect("Demod stats poisoned").clone();
+        let stats = Line::from(format!(" preambles {} good {} corrected 1 bit {} 2 bit {} ",
+            stats.preambles, stats.good, stats.corrected_one_bit, stats.corrected_two_bit)).right_aligned();
+
+        let footer = Paragraph::new(self.last_event.as_ref().map_or_else(String::new, |event| event.to_string()))
+            .block(Block::bordered().title(stats));
+
         frame.render_widget(table, layout[0]);
-        // frame.render_widget(footer, layout[1]);
+        frame.render_widget(footer, layout[1]);
     }
 
         /// Reads the crossterm events and updates the state of [`App`].
@@ -132,10 +175,10 @@ impl App {
     }
 }
 
-pub fn interactive_display_thread_tui(rx: Receiver<AdsbPacket>) {
+pub fn interactive_display_thread_tui(rx: Receiver<AdsbPacket>, tracker: AircraftTracker, stats: Arc<Mutex<DemodStats>>) {
     color_eyre::install().expect("Cannot install color eye try stream display mode");
     let terminal = ratatui::init();
-    App::new().run(terminal, rx).expect("Interactive mode terminal render died");
+    App::new(tracker, stats).run(terminal, rx).expect("Interactive mode terminal render died");
     ratatui::restore();
 }
 
@@ -5,22 +5,29 @@
 use axum::{routing::{get, get_service}, Json, Router};
 use tokio::sync::broadcast;
 use tower_http::services::ServeDir;
-use std::{collections::HashMap, net::SocketAddr};
+use std::net::SocketAddr;
 use serde::Serialize;
 
 use axum::extract::ws::{WebSocketUpgrade, WebSocket, Message};
-use axum::extract::ConnectInfo;
+use axum::extract::{ConnectInfo, Path};
+use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::routing::get as axum_get;
 use futures_util::{StreamExt, SinkExt};
 use std::net::SocketAddr as StdSocketAddr;
 
 use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use crate::adsb::aircraft::AircraftTrack;
+use crate::adsb::demod::DemodStats;
 use crate::adsb::packet::AdsbPacket;
-use crate::adsb::aircraft::{Aircraft, handle_aircraft_update};
+use crate::adsb::tracker::AircraftTracker;
 
 const WEB_DIR: &str = "adsb_frontend/dist";
+/// Time between printing the number of packets and the demodulator statistics
+const STATS_LOG_INTERVAL: Duration = Duration::from_secs(10);
 
 #[derive(Serialize)]
 struct MyData {
@@ -35,12 +42,26 @@ async fn get_data() -> Json<MyData> {
     })
 }
 
+/// Get the track history of an aircraft
+///
+/// `icao` - the address of the aircraft
+/// `tracker` - the tracker holding the aircraft
+async fn get_track(Path(icao): Path<u32>, tracker: Arc<Mutex<AircraftTracker>>) -> Result<Json<AircraftTrack>, StatusCode> {
+    let tracker = tracker.lock().expect("Tracker poisoned");
+
+    match tracker.get(icao) {
+        Some(aircraft) => Ok(Json(aircraft.get_track_history())),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
 // Build the axum router
-fn build_app(ws_tx: broadcast::Sender<String>) -> Router {
+fn build_app(ws_tx: broadcast::Sender<String>, tracker: Arc<Mutex<AircraftTracker>>) -> Router {
     let static_files_service = get_service(ServeDir::new(WEB_DIR));
 
     Router::new()
         .route("/api/data", get(get_data))
+        .route("/api/track/:icao", get(move |icao: Path<u32>| get_track(icao, tracker.clone())))
         .route("/ws", axum_get(move |ws: WebSocketUpgrade, addr: ConnectInfo<StdSocketAddr>| {
             ws_handler(ws, addr, ws_tx.clone())
         }))
@@ -48,8 +69,8 @@ fn build_app(ws_tx: broadcast::Sender<String>) -> Router {
 }
 
 // Run the server (async)
-async fn run_server(ws_tx: broadcast::Sender<String>) {
-    let app = build_app(ws_tx);
+async fn run_server(ws_tx: broadcast::Sender<String>, tracker: Arc<Mutex<AircraftTracker>>) {
+    let app = build_app(ws_tx, tracker);
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
     println!("Listening on http://{}", addr);
@@ -96,7 +117,9 @@ async fn handle_socket(socket: WebSocket, tx: broadcast::Sender<String>) {
 /// Handle the web interface for the ADS-B system.
 /// 
 /// `rx` - the receiver for ADS-B packets
-pub fn web_interface_thread(rx: Receiver<AdsbPacket>) {
+/// `tracker` - the tracker holding the aircraft, shared with the server for the track history
+/// `stats` - the demodulator statistics logged while the server runs
+pub fn web_interface_thread(rx: Receiver<AdsbPacket>, mut tracker: AircraftTracker, stats: Arc<Mutex<DemodStats>>) {
     // Create the Tokio runtime
     let rt = tokio::runtime::Runtime::new().unwrap();
 
@@ -105,27 +128,45 @@ pub fn web_interface_thread(rx: Receiver<AdsbPacket>) {
         // Broadcast channel for WebSocket messages
         let (ws_tx, _) = broadcast::channel::<String>(100);
 
+        let events = tracker.subscribe();
+        let tracker = Arc::new(Mutex::new(tracker));
+
         // Spawn the web server in the background
         let server_tx = ws_tx.clone();
+        let server_tracker = Arc::clone(&tracker);
         tokio::spawn(async move {
-            run_server(server_tx).await;
+            run_server(server_tx, server_tracker).await;
         });
 
         let mut num_packets = 0;
-        let mut aircrafts: HashMap<u32, Aircraft> = HashMap::new();
+        let mut last_log = Instant::now();
 
         loop {
-            while let Ok(packet) = rx.try_recv() {
-                num_packets += 1;
-                let aircraft = handle_aircraft_update(packet, &mut aircrafts);
-                if let Some(aircraft) = aircraft {
-                    let summary = aircraft.get_summary();
+            {
+                let mut tracker = tracker.lock().expect("Tracker poisoned");
+                while let Ok(packet) = rx.try_recv() {
+                    num_packets += 1;
+                    let summary = tracker.handle_packet(packet).get_summary();
                     if let Ok(json) = serde_json::to_string(&summary) {
                         // Broadcast summary to all WebSocket clients
                         println!("Broadcasting aircraft summary: {}", json);
                         let _ = ws_tx.send(json);
                     }
                 }
+                tracker.prune(Instant::now());
+            }
+
+            // Lifecycle events are tagged with their type to tell them apart from summaries
+            while let Ok(event) = events.try_recv() {
+                if let Ok(json) = serde_json::to_string(&event) {
+                    let _ = ws_tx.send(json);
+                }
+            }
+
+            if last_log.elapsed() >= STATS_LOG_INTERVAL {
+                println!("Received {} packets", num_packets);
+                print!("{}", stats.lock().expect("Demod stats poisoned"));
+                last_log = Instant::now();
             }
 
             // Avoid busy loop
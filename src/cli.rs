@@ -30,7 +30,87 @@ pub enum Commands {
 
         #[arg(short, long)]
         playback: Option<String>,
+
+        #[command(flatten)]
+        args: AdsbArgs,
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct AdsbArgs {
+    /// Receiver latitude in degrees used as a reference for position decoding
+    #[arg(long = "lat", requires = "longitude", allow_negative_numbers = true)]
+    pub latitude: Option<f64>,
+
+    /// Receiver longitude in degrees used as a reference for position decoding
+    #[arg(long = "lon", requires = "latitude", allow_negative_numbers = true)]
+    pub longitude: Option<f64>,
+
+    /// Maximum range from the receiver in nautical miles, positions further away are rejected
+    #[arg(long = "max-range", default_value_t = 300.0)]
+    pub max_range: f64,
+
+    /// Seconds of track history kept for each aircraft
+    #[arg(long = "track-history", default_value_t = 900)]
+    pub track_history: u32,
+
+    /// Maximum number of positions kept in the track history of each aircraft
+    #[arg(long = "track-points", default_value_t = 1000)]
+    pub track_points: usize,
+
+    /// Seconds without a packet before an aircraft is removed
+    #[arg(long = "aircraft-timeout", default_value_t = 60)]
+    pub aircraft_timeout: u32,
+
+    /// Number of bit errors that can be corrected in extended squitters
+    #[arg(long = "error-correction", default_value_t = ErrorCorrection::OneBit)]
+    pub error_correction: ErrorCorrection,
+
+    /// Sample rate in Hz to run the sdr at, at least 2 MS/s
+    #[arg(long = "sample-rate", default_value_t = 2_000_000.0, value_parser = parse_sample_rate)]
+    pub sample_rate: f64,
+
+    /// Demodulator used to find frames in the sdr samples [default: 2-sample at 2 MS/s otherwise correlation]
+    #[arg(long = "demodulator")]
+    pub demodulator: Option<DemodulatorType>,
+
+    /// Number of threads used to demodulate the sdr samples
+    #[arg(long = "workers", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub workers: u32,
+
+    /// Send playback samples as fast as they can be processed instead of at twice the recorded rate
+    #[arg(long = "fast-playback")]
+    pub fast_playback: bool,
+}
+
+impl AdsbArgs {
+    /// Get the demodulator to use for the sample rate
+    ///
+    /// returns the demodulator or an error if the chosen one can't run at the sample rate
+    pub fn get_demodulator(&self) -> Result<DemodulatorType, String> {
+        match self.demodulator {
+            Some(DemodulatorType::TwoSample) if self.sample_rate != 2_000_000.0 => {
+                Err(format!("the 2-sample demodulator only supports 2 MS/s not {} MS/s, use --demodulator correlation instead", self.sample_rate / 1e6))
+            },
+            Some(demodulator) => Ok(demodulator),
+            None if self.sample_rate == 2_000_000.0 => Ok(DemodulatorType::TwoSample),
+            None => Ok(DemodulatorType::Correlation),
+        }
+    }
+}
+
+/// Parse a sample rate that the demodulators can run at
+///
+/// `arg` - the sample rate in Hz
+///
+/// returns the sample rate or an error if it is below 2 MS/s
+fn parse_sample_rate(arg: &str) -> Result<f64, String> {
+    let sample_rate: f64 = arg.parse().map_err(|_| format!("{} isn't a number", arg))?;
+    if !sample_rate.is_finite() || sample_rate < 2_000_000.0 {
+        return Err(String::from("the sample rate must be at least 2 MS/s"));
     }
+
+    Ok(sample_rate)
 }
 
 #[derive(Args, Debug)]
@@ -68,4 +148,101 @@ impl std::fmt::Display for DisplayMode {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ErrorCorrection {
+    Off,
+    #[value(name = "1-bit")]
+    OneBit,
+    /// Two bit errors are only corrected in DF17 frames with a strong signal
+    #[value(name = "2-bit")]
+    TwoBit,
+}
+
+impl ErrorCorrection {
+    /// Returns the maximum number of bits that can be corrected
+    pub fn max_errors(&self) -> usize {
+        match self {
+            Self::Off => 0,
+            Self::OneBit => 1,
+            Self::TwoBit => 2,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCorrection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Off => "off",
+            Self::OneBit => "1-bit",
+            Self::TwoBit => "2-bit"
+        };
+
+        write!(f, "{}", name)?;
+
+        Ok(())
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum DemodulatorType {
+    /// Fixed demodulator for exactly 2 MS/s
+    #[value(name = "2-sample")]
+    TwoSample,
+    /// Preamble correlation for any sample rate of 2 MS/s or more
+    Correlation,
+}
+
+impl std::fmt::Display for DemodulatorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::TwoSample => "2-sample",
+            Self::Correlation => "correlation"
+        };
+
+        write!(f, "{}", name)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_adsb_args(args: &[&str]) -> Result<AdsbArgs, clap::Error> {
+        let cli = CliArgs::try_parse_from([&["air_rs", "adsb"], args].concat())?;
+
+        match cli.command {
+            Commands::Adsb { args, .. } => Ok(args),
+            _ => panic!("Not an adsb command"),
+        }
+    }
+
+    #[test]
+    fn test_demodulator_default() {
+        let args = get_adsb_args(&[]).unwrap();
+        assert_eq!(args.get_demodulator(), Ok(DemodulatorType::TwoSample));
+
+        let args = get_adsb_args(&["--sample-rate", "2400000"]).unwrap();
+        assert_eq!(args.get_demodulator(), Ok(DemodulatorType::Correlation));
+    }
+
+    #[test]
+    fn test_demodulator_sample_rate_mismatch() {
+        let args = get_adsb_args(&["--sample-rate", "2400000", "--demodulator", "2-sample"]).unwrap();
+        assert!(args.get_demodulator().is_err());
+
+        let args = get_adsb_args(&["--sample-rate", "2400000", "--demodulator", "correlation"]).unwrap();
+        assert_eq!(args.get_demodulator(), Ok(DemodulatorType::Correlation));
+    }
+
+    #[test]
+    fn test_sample_rate_too_low() {
+        assert!(get_adsb_args(&["--sample-rate", "1000000"]).is_err());
+        assert!(get_adsb_args(&["--sample-rate", "fast"]).is_err());
+        assert!(get_adsb_args(&["--sample-rate", "NaN"]).is_err());
+        assert!(get_adsb_args(&["--sample-rate", "inf"]).is_err());
+    }
+}
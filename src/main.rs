@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
 
 mod utils;
 
@@ -20,7 +20,12 @@ fn main() {
 
     match cli.command {
         Commands::List => list_devices().expect("Couldn't start sdr sub process"),
-        Commands::Adsb {device, mode, playback} => launch_adsb(device, mode, playback),
+        Commands::Adsb {device, mode, playback, args} => {
+            if let Err(message) = args.get_demodulator() {
+                CliArgs::command().error(ErrorKind::ArgumentConflict, message).exit();
+            }
+            launch_adsb(device, mode, playback, args)
+        },
         Commands::Receive {device, args} => launch_receive(device, args),
     };
 }
@@ -2,6 +2,7 @@ use num_complex::Complex;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Read, Write};
+use std::sync::OnceLock;
 
 /// Save complex data in SatDump-compatible `.c16` format (I then Q)
 pub fn save_data(data: &[Complex<i16>], name: String) -> Result<(), Box<dyn std::error::Error>> {
@@ -42,12 +43,114 @@ pub fn load_data(filename: String) -> Result<Vec<Complex<i16>>, Box<dyn std::err
     Ok(data)
 }
 
+/// A complex sample read from the sdr
+pub trait Sample: Copy + Send + 'static {
+    /// Calculate the magnitude of every sample in the buffer on the scale of 16 bit samples
+    fn get_magnitude(buf: &[Self]) -> Vec<u32>;
+}
+
+impl Sample for Complex<i16> {
+    fn get_magnitude(buf: &[Self]) -> Vec<u32> {
+        get_magnitude(buf)
+    }
+}
+
+impl Sample for Complex<i8> {
+    fn get_magnitude(buf: &[Self]) -> Vec<u32> {
+        get_magnitude_i8(buf)
+    }
+}
+
 /// Calculate the magnitude of every complex pair in the buffer
+///
+/// The loop is branch free so the compiler vectorises it.
 pub fn get_magnitude(buf: &[Complex<i16>]) -> Vec<u32> {
-    let result: Vec<u32> = buf.iter()
-        .map(|c| ((c.re as f64).powi(2) + (c.im as f64).powi(2)).sqrt() as u32)
-        .collect();
+    buf.iter().map(get_sample_magnitude).collect()
+}
+
+/// Calculate the integer magnitude of a single sample
+#[inline(always)]
+fn get_sample_magnitude(c: &Complex<i16>) -> u32 {
+    // The largest power is 2 * 32768^2 which fits in a u32
+    let power = (c.re as i32 * c.re as i32) as u32 + (c.im as i32 * c.im as i32) as u32;
+
+    // Root in single precision which packs twice the lanes of a double. Adding 2^23 rounds the
+    // root to an integer in the low bits of the float which avoids the saturating conversion.
+    let (re, im) = (c.re as i32 as f32, c.im as i32 as f32);
+    let root = ((re * re + im * im).sqrt() + 8_388_608.0).to_bits() - 0x4B00_0000;
 
-    result
+    // The power is only approximate above 2^24 so the root can be one too large. The root is
+    // at most 46341 so its square and the next fit in a u32.
+    let root = root - (root * root > power) as u32;
+    root + ((root + 1) * (root + 1) <= power) as u32
 }
 
+/// Calculate the magnitude of every 8 bit complex pair in the buffer
+///
+/// Every possible sample is looked up in a table built on first use. The result is scaled to
+/// match the magnitudes of 16 bit samples.
+pub fn get_magnitude_i8(buf: &[Complex<i8>]) -> Vec<u32> {
+    static TABLE: OnceLock<Vec<u16>> = OnceLock::new();
+
+    let table = TABLE.get_or_init(|| {
+        (0..=u16::MAX)
+            .map(|i| {
+                let re = ((i >> 8) as u8 as i8) as f64 * 256.0;
+                let im = (i as u8 as i8) as f64 * 256.0;
+                (re * re + im * im).sqrt() as u16
+            })
+            .collect()
+    });
+
+    buf.iter()
+        .map(|c| table[((c.re as u8 as usize) << 8) | c.im as u8 as usize] as u32)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The original floating point magnitude used as a reference
+    fn get_magnitude_f64(buf: &[Complex<i16>]) -> Vec<u32> {
+        buf.iter()
+            .map(|c| ((c.re as f64).powi(2) + (c.im as f64).powi(2)).sqrt() as u32)
+            .collect()
+    }
+
+    #[test]
+    fn test_magnitude_matches_f64() {
+        let mut buf: Vec<Complex<i16>> = (0..100_000u32)
+            .map(|i| {
+                let seed = i.wrapping_mul(2_654_435_761);
+                Complex::new(seed as i16, (seed >> 16) as i16)
+            })
+            .collect();
+        buf.extend_from_slice(&[
+            Complex::new(i16::MIN, i16::MIN),
+            Complex::new(i16::MAX, i16::MIN),
+            Complex::new(0, 0),
+            Complex::new(-3, 4),
+        ]);
+
+        assert_eq!(get_magnitude(&buf), get_magnitude_f64(&buf));
+    }
+
+    #[test]
+    fn test_magnitude_large_powers() {
+        // Powers above 2^24 where the single precision root is only approximate
+        let buf: Vec<Complex<i16>> = (i16::MIN..=i16::MAX)
+            .flat_map(|re| [Complex::new(re, 0), Complex::new(re, 1), Complex::new(re, -4096), Complex::new(re, i16::MIN), Complex::new(re, i16::MAX)])
+            .collect();
+
+        assert_eq!(get_magnitude(&buf), get_magnitude_f64(&buf));
+    }
+
+    #[test]
+    fn test_magnitude_i8() {
+        let buf = [Complex::new(0i8, 0i8), Complex::new(3, -4), Complex::new(-128, -128), Complex::new(127, 0)];
+        let wide: Vec<Complex<i16>> = buf.iter().map(|c| Complex::new(c.re as i16 * 256, c.im as i16 * 256)).collect();
+
+        assert_eq!(get_magnitude_i8(&buf), get_magnitude_f64(&wide));
+    }
+}